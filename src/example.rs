@@ -24,10 +24,25 @@ impl ConventionalCard {
         let pos = egui::Pos2::new(x, y);
         ConventionalCard { suit, rank, pos }
     }
-    #[allow(dead_code)]
-    fn img_path(&self) -> String {
+    /// Card type in [`ConventionalCardIter`] order, i.e. all ranks of a suit in a row.
+    pub fn t(&self) -> usize {
+        self.suit as usize * Rank::len() + self.rank as usize
+    }
+    /// Reverses [`ConventionalCard::t`], [`None`] for `t >= 52`.
+    pub fn from_t(t: usize) -> Option<Self> {
+        if t >= Suit::len() * Rank::len() {
+            return None;
+        }
+        Some(ConventionalCard {
+            suit: (t / Rank::len()).into(),
+            rank: (t % Rank::len()).into(),
+            pos: Default::default(),
+        })
+    }
+    /// File name of the card's image, e.g. `1_heart.png` for the ace of hearts.
+    pub(crate) fn img_name(&self) -> String {
         format!(
-            "http://127.0.0.1:8080/media/img_cards/{}_{}.png",
+            "{}_{}.png",
             self.rank as usize + 1,
             self.suit.to_string().to_lowercase()
         )
@@ -343,8 +358,8 @@ impl HandLayout {
                             }
                             todo!("Paint card here");
                         }
-                        if selected.is_some() {
-                            if let Some(_card) = self.cards.get(selected.unwrap()) {
+                        if let Some(selected) = selected {
+                            if let Some(_card) = self.cards.get(selected) {
                                 #[allow(unreachable_code)]
                                 #[allow(clippy::diverging_sub_expression)]
                                 let _card_pos = next_pos
@@ -1,17 +1,39 @@
+use card::DirectoryCardType;
 use egui::Context;
 use std::cell::RefCell;
-use std::collections::{hash_map, HashMap};
+use std::collections::BTreeMap;
 use std::rc::Rc;
 pub mod card;
 pub mod field;
+#[cfg(target_arch = "wasm32")]
+mod hash_route;
+pub mod modal;
+pub mod navigation;
+pub mod registry;
 pub mod screen;
-use screen::{MainMenu, ScreenWidget};
+pub mod settings;
+pub mod shortcuts;
+pub mod toasts;
+use modal::ModalLayer;
+use navigation::{Navigator, Route, TransitionKind};
+use registry::ScreenRegistry;
+use screen::{Game, GameSetupScreen, MainMenu, ScreenWidget, SettingsScreen};
+use settings::Settings;
+use shortcuts::{ShortcutAction, ShortcutRegistry};
+use toasts::Toasts;
 
 pub struct App {
-    // TODO Make custom struct of this HashMap so that screens can dynamically register other screens
-    screens: HashMap<String, Rc<RefCell<dyn ScreenWidget>>>,
+    screens: ScreenRegistry,
     default_screen: Rc<RefCell<dyn ScreenWidget>>,
-    current_screen: Rc<RefCell<String>>,
+    navigator: Navigator,
+    shortcuts: ShortcutRegistry,
+    modals: ModalLayer,
+    toasts: Toasts,
+    settings: Rc<RefCell<Settings>>,
+    entered: bool,
+    restored: bool,
+    #[cfg(target_arch = "wasm32")]
+    hash_router: Option<hash_route::HashRouter>,
 }
 
 impl Default for App {
@@ -21,60 +43,316 @@ impl Default for App {
 }
 
 impl App {
+    /// Creates an app with the built-in main menu, game setup and settings screens.
     #[allow(unused)]
     pub fn new(main_screen: Option<Rc<RefCell<dyn ScreenWidget>>>) -> Self {
         let default_screen = main_screen.unwrap_or_else(|| Rc::new(RefCell::new(MainMenu::new())));
-        let current_screen = Rc::new(RefCell::new(String::from("main")));
-        let mut screens = HashMap::new();
-        screens.insert(String::from("main"), Rc::clone(&default_screen));
+        let navigator = Navigator::new(Route::MainMenu);
+        let settings = Rc::new(RefCell::new(Settings::default()));
+        let toasts = Toasts::new();
+        let mut screens = ScreenRegistry::new();
+        screens.replace(Route::MainMenu, Rc::clone(&default_screen));
+        screens.replace(
+            Route::GameSetup,
+            Rc::new(RefCell::new(GameSetupScreen::new(
+                Rc::clone(&settings),
+                toasts.clone(),
+            ))),
+        );
+        screens.replace(
+            Route::Settings,
+            Rc::new(RefCell::new(SettingsScreen::new(Rc::clone(&settings)))),
+        );
         Self {
             screens,
             default_screen,
-            current_screen,
+            navigator,
+            shortcuts: ShortcutRegistry::new(),
+            modals: ModalLayer::new(),
+            toasts,
+            settings,
+            entered: false,
+            restored: false,
+            #[cfg(target_arch = "wasm32")]
+            hash_router: None,
         }
     }
+    const NAVIGATOR_KEY: &'static str = "mcg_visual_navigator";
+    const SCREENS_KEY: &'static str = "mcg_visual_screens";
+
+    /// Restores everything that got persisted by [`eframe::App::save`], so a reload
+    /// resumes on the same screen with the same state.
+    pub fn with_storage(mut self, storage: Option<&dyn eframe::Storage>) -> Self {
+        let Some(storage) = storage else {
+            return self;
+        };
+        if let Some(settings) = Settings::load(storage) {
+            *self.settings.borrow_mut() = settings;
+        }
+        let states: BTreeMap<Route, String> =
+            eframe::get_value(storage, Self::SCREENS_KEY).unwrap_or_default();
+        for (route, state) in states {
+            let screen = match self.screens.get(&route) {
+                Some(screen) => screen,
+                None => match self.builtin_screen(&route) {
+                    Some(screen) => {
+                        self.screens.replace(route, Rc::clone(&screen));
+                        screen
+                    }
+                    None => continue,
+                },
+            };
+            screen.borrow_mut().restore_state(&state);
+        }
+        if let Some(navigator) = eframe::get_value(storage, Self::NAVIGATOR_KEY) {
+            self.navigator = navigator;
+            self.restored = true;
+        }
+        self
+    }
+    /// Screens that aren't registered up front but get created on the fly.
+    fn builtin_screen(&self, route: &Route) -> Option<Rc<RefCell<dyn ScreenWidget>>> {
+        match route {
+            Route::Game => Some(Rc::new(RefCell::new(
+                Game::<DirectoryCardType>::new().toasts(self.toasts.clone()),
+            ))),
+            _ => None,
+        }
+    }
+    pub fn settings(&self) -> Rc<RefCell<Settings>> {
+        Rc::clone(&self.settings)
+    }
+    /// Handle to the app-wide notifications, for screens registered from outside.
+    pub fn toasts(&self) -> Toasts {
+        self.toasts.clone()
+    }
     #[allow(clippy::result_unit_err)]
     pub fn register_screen(
         &mut self,
-        name: String,
+        route: Route,
         screen: Rc<RefCell<dyn ScreenWidget>>,
     ) -> Result<(), ()> {
-        if let hash_map::Entry::Vacant(e) = self.screens.entry(name) {
-            e.insert(screen);
-            Ok(())
-        } else {
-            Err(())
+        self.screens.register(route, screen)
+    }
+    fn screen(&self, route: &Route) -> Rc<RefCell<dyn ScreenWidget>> {
+        self.screens
+            .get(route)
+            .unwrap_or_else(|| Rc::clone(&self.default_screen))
+    }
+    /// Carries out a pending navigation request and runs the screens' hooks for it,
+    /// `from` is the screen shown until now. Returns whether the screen changed.
+    fn apply_navigation(&mut self, ctx: &Context, from: &Rc<RefCell<dyn ScreenWidget>>) -> bool {
+        let Some(transition) = self.navigator.apply(&self.screens) else {
+            return false;
+        };
+        // The previous screen may have unregistered itself, so it's notified through
+        // the handle it got updated with.
+        self.shortcuts.screen_changed();
+        self.modals.screen_changed();
+        let mut from = from.borrow_mut();
+        match transition.kind {
+            TransitionKind::Push | TransitionKind::Forward => from.on_suspend(ctx),
+            TransitionKind::Replace | TransitionKind::Back => from.on_exit(ctx),
+        }
+        drop(from);
+        for location in &transition.dropped {
+            if let Some(screen) = self.screens.get(&location.route) {
+                screen.borrow_mut().on_exit(ctx);
+            }
+        }
+        let to = self.screen(&transition.to.route);
+        match transition.kind {
+            TransitionKind::Back => to.borrow_mut().on_resume(ctx),
+            _ => to.borrow_mut().on_enter(ctx),
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(router) = &self.hash_router {
+            router.sync(&transition.to, transition.kind);
         }
+        ctx.request_repaint();
+        true
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
-        let current_screen = match self.screens.get_mut(&self.current_screen.borrow().clone()) {
-            Some(screen) => Rc::clone(screen),
-            None => Rc::clone(&self.default_screen),
+        // Replaced by a deep link on the web build
+        #[cfg_attr(not(target_arch = "wasm32"), allow(unused_mut))]
+        let mut current_screen = self.screen(self.navigator.route());
+        if !self.entered {
+            self.settings.borrow().apply(ctx);
+            if self.restored {
+                current_screen.borrow_mut().on_resume(ctx);
+            } else {
+                current_screen.borrow_mut().on_enter(ctx);
+            }
+            self.entered = true;
+            #[cfg(target_arch = "wasm32")]
+            {
+                // A link like `#/game_setup` wins over the restored session
+                if let Some(location) = hash_route::HashRouter::initial_location() {
+                    if &location != self.navigator.current() {
+                        self.navigator.push_location(location);
+                        self.apply_navigation(ctx, &current_screen);
+                        current_screen = self.screen(self.navigator.route());
+                    }
+                }
+                self.hash_router = hash_route::HashRouter::new(ctx);
+                if let Some(router) = &self.hash_router {
+                    router.sync(self.navigator.current(), TransitionKind::Replace);
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(router) = &self.hash_router {
+            router.poll(&mut self.navigator);
+        }
+        self.shortcuts.begin_frame();
+        // An open modal owns the keyboard, e.g. Escape closes it instead of going back
+        if !self.modals.is_open() {
+            current_screen
+                .borrow()
+                .declare_shortcuts(&mut self.shortcuts);
+            self.shortcuts.consume(ctx);
+        }
+        current_screen.borrow_mut().update(
+            &mut self.navigator,
+            &mut self.screens,
+            &mut self.shortcuts,
+            &mut self.modals,
+            ctx,
+            frame,
+        );
+        if self.shortcuts.take(&ShortcutAction::Back) {
+            self.navigator.back();
+        }
+        self.shortcuts.show_help(ctx);
+        self.modals.show(ctx);
+        self.toasts.show(ctx);
+        self.apply_navigation(ctx, &current_screen);
+    }
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.borrow().save(storage);
+        eframe::set_value(storage, Self::NAVIGATOR_KEY, &self.navigator);
+        let states: BTreeMap<Route, String> = self
+            .screens
+            .iter()
+            .filter_map(|(route, screen)| Some((route.clone(), screen.borrow().save_state()?)))
+            .collect();
+        eframe::set_value(storage, Self::SCREENS_KEY, &states);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::modal::ModalLayer;
+    use crate::game::registry::ScreenRegistry;
+
+    /// Writes every hook call into a log shared by all screens.
+    struct Recorder {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+    impl Recorder {
+        fn record(&self, hook: &str) {
+            self.log.borrow_mut().push(format!("{} {hook}", self.name));
+        }
+    }
+    impl ScreenWidget for Recorder {
+        fn update(
+            &mut self,
+            _navigator: &mut Navigator,
+            _screens: &mut ScreenRegistry,
+            _shortcuts: &mut ShortcutRegistry,
+            _modals: &mut ModalLayer,
+            _ctx: &Context,
+            _frame: &mut eframe::Frame,
+        ) {
+        }
+        fn on_enter(&mut self, _ctx: &Context) {
+            self.record("enter");
+        }
+        fn on_exit(&mut self, _ctx: &Context) {
+            self.record("exit");
+        }
+        fn on_suspend(&mut self, _ctx: &Context) {
+            self.record("suspend");
+        }
+        fn on_resume(&mut self, _ctx: &Context) {
+            self.record("resume");
+        }
+    }
+
+    fn app(log: &Rc<RefCell<Vec<String>>>) -> App {
+        let recorder = |name| -> Rc<RefCell<dyn ScreenWidget>> {
+            Rc::new(RefCell::new(Recorder {
+                name,
+                log: Rc::clone(log),
+            }))
         };
-        let next_screen = Rc::clone(&self.current_screen);
-        current_screen.borrow_mut().update(next_screen, ctx, frame);
-        // TODO Create custom screen for text sizes
-        // use egui::FontFamily::Proportional;
-        // use egui::FontId;
-        // use egui::TextStyle::*;
-        // use std::collections::BTreeMap;
-        // egui::CentralPanel::default().show(ctx, |ui| {
-        //     let size = 30.0;
-        //     let text_styles: BTreeMap<_, _> = [
-        //         (Heading, FontId::new(size, Proportional)),
-        //         (Name("Heading2".into()), FontId::new(size, Proportional)),
-        //         (Name("Context".into()), FontId::new(size, Proportional)),
-        //         (Body, FontId::new(size, Proportional)),
-        //         (Monospace, FontId::new(size, Proportional)),
-        //         (Button, FontId::new(size, Proportional)),
-        //         (Small, FontId::new(size, Proportional)),
-        //     ]
-        //     .into();
-        //     // Mutate global styles with new text styles
-        //     ctx.all_styles_mut(move |style| style.text_styles = text_styles.clone());
-        // }
+        let mut app = App::new(Some(recorder("main")));
+        for name in ["a", "b", "c"] {
+            app.register_screen(custom(name), recorder(name)).unwrap();
+        }
+        app
+    }
+    /// Carries out what `request` asks the navigator for and returns the hooks that ran.
+    fn navigate(
+        app: &mut App,
+        log: &Rc<RefCell<Vec<String>>>,
+        request: impl FnOnce(&mut Navigator),
+    ) -> Vec<String> {
+        let current = app.screen(app.navigator.route());
+        request(&mut app.navigator);
+        app.apply_navigation(&Context::default(), &current);
+        log.take()
+    }
+    fn custom(name: &str) -> Route {
+        Route::Custom(name.to_string())
+    }
+
+    #[test]
+    fn hooks_run_in_order() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut app = app(&log);
+        assert_eq!(
+            navigate(&mut app, &log, |nav| nav.push(custom("a"))),
+            ["main suspend", "a enter"]
+        );
+        navigate(&mut app, &log, |nav| nav.push(custom("b")));
+        assert_eq!(
+            navigate(&mut app, &log, |nav| nav.back()),
+            ["b exit", "a resume"]
+        );
+        assert_eq!(
+            navigate(&mut app, &log, |nav| nav.forward()),
+            ["a suspend", "b enter"]
+        );
+        assert_eq!(
+            navigate(&mut app, &log, |nav| nav.replace(custom("c"))),
+            ["b exit", "c enter"]
+        );
+        // The current screen closes first, then the dropped ones, then the target resumes
+        assert_eq!(
+            navigate(&mut app, &log, |nav| nav.pop_to(Route::MainMenu)),
+            ["c exit", "a exit", "main resume"]
+        );
+        assert!(navigate(&mut app, &log, |nav| nav.back()).is_empty());
+        assert!(navigate(&mut app, &log, |nav| nav.push(custom("nowhere"))).is_empty());
+        assert_eq!(app.navigator.route(), &Route::MainMenu);
+    }
+
+    #[test]
+    fn screens_that_unregistered_themselves_still_exit() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut app = app(&log);
+        navigate(&mut app, &log, |nav| nav.push(custom("a")));
+        // Like a screen dropping itself during its update
+        let current = app.screen(&custom("a"));
+        app.screens.unregister(&custom("a"));
+        app.navigator.back();
+        assert!(app.apply_navigation(&Context::default(), &current));
+        assert_eq!(log.take(), ["a exit", "main resume"]);
     }
 }
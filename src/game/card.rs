@@ -1,64 +1,451 @@
+use crate::game::toasts::Toasts;
 #[cfg(target_arch = "wasm32")]
 use crate::openDirectoryPicker;
+use bits::{check_type, check_width, EncodingError};
 use egui::{Image, Vec2};
+use manifest::Manifest;
+use serde::de::{self, EnumAccess, SeqAccess, VariantAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 #[allow(unused_imports)]
 use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::Index;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 #[allow(unused_imports)]
 use std::rc::Rc;
 use std::slice::Iter;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::js_sys::Array;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 
+pub mod assets;
+pub mod atlas;
+pub mod bits;
+pub mod elgamal;
+pub mod embedded;
+pub mod joint;
+pub mod manifest;
+pub mod painted;
+pub mod shuffle;
+
 pub trait CardEncoding {
+    /// What [`CardEncoding::mask`] needs, e.g. the public key of the players.
+    type MaskKey;
+    /// What [`CardEncoding::open`] needs, e.g. a secret key.
+    type OpenKey;
     fn t(&self) -> Option<usize>;
     fn is_masked(&self) -> bool;
     fn is_open(&self) -> bool {
         !self.is_masked()
     }
-    fn mask(self) -> Self;
-    fn open(self) -> Self;
+    fn mask(self, key: &Self::MaskKey) -> Self;
+    /// Returns the card unchanged if `key` can't open it.
+    fn open(self, key: &Self::OpenKey) -> Self;
+    /// How the card lies on the table, encodings without orientation are always upright.
+    fn orientation(&self) -> Orientation {
+        Orientation::Upright
+    }
+    /// Fails if the encoding can't store `orientation`.
+    #[allow(clippy::result_unit_err)]
+    fn set_orientation(&mut self, orientation: Orientation) -> Result<(), ()> {
+        match orientation {
+            Orientation::Upright => Ok(()),
+            _ => Err(()),
+        }
+    }
+    /// Number of bits [`CardEncoding::to_bits`] uses for a [`CardConfig`] of width `w`.
+    fn bit_width(w: u32) -> u32;
+    /// Lossless encoding into the lowest [`CardEncoding::bit_width`] bits.
+    /// Fails if the card's type isn't one of `config`.
+    fn to_bits(&self, config: &impl CardConfig) -> Result<u64, EncodingError>;
+    /// Reverses [`CardEncoding::to_bits`], rejecting bits no card of `config` encodes to.
+    fn from_bits(bits: u64, config: &impl CardConfig) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
 }
+/// Masking is purely cosmetic, the type stays readable for anybody holding the card.
 impl CardEncoding for SimpleCard {
+    type MaskKey = ();
+    type OpenKey = ();
+
     fn t(&self) -> Option<usize> {
         match self {
-            SimpleCard::Open(t) => Some(*t),
-            SimpleCard::Masked(_) => None,
+            SimpleCard::Open(t, _) => Some(*t),
+            SimpleCard::Masked(..) => None,
         }
     }
     fn is_masked(&self) -> bool {
-        matches!(self, SimpleCard::Masked(_))
+        matches!(self, SimpleCard::Masked(..))
     }
-    fn mask(self) -> Self {
+    fn mask(self, _key: &()) -> Self {
         match self {
-            SimpleCard::Open(t) => Self::Masked(Some(t)),
+            SimpleCard::Open(t, orientation) => Self::Masked(Some(t), orientation),
             _ => self,
         }
     }
-    fn open(self) -> Self {
+    fn open(self, _key: &()) -> Self {
         match self {
-            SimpleCard::Masked(Some(t)) => Self::Open(t),
+            SimpleCard::Masked(Some(t), orientation) => Self::Open(t, orientation),
             _ => self,
         }
     }
+    fn orientation(&self) -> Orientation {
+        match self {
+            SimpleCard::Open(_, orientation) | SimpleCard::Masked(_, orientation) => *orientation,
+        }
+    }
+    fn set_orientation(&mut self, orientation: Orientation) -> Result<(), ()> {
+        match self {
+            SimpleCard::Open(_, current) | SimpleCard::Masked(_, current) => *current = orientation,
+        }
+        Ok(())
+    }
+    /// A masked flag, a flag whether a masked card's type is known, two bits of
+    /// [`Orientation`] and the type itself.
+    fn bit_width(w: u32) -> u32 {
+        w + 4
+    }
+    fn to_bits(&self, config: &impl CardConfig) -> Result<u64, EncodingError> {
+        let (t, flags) = match self {
+            SimpleCard::Open(t, _) => (check_type(*t, config)?, 0b00),
+            SimpleCard::Masked(None, _) => (0, 0b01),
+            SimpleCard::Masked(Some(t), _) => (check_type(*t, config)?, 0b11),
+        };
+        // A config with more types than fit into its width
+        let t = check_width(t as u64, config.w())?;
+        Ok(t << 4 | (self.orientation().quarter_turns() as u64) << 2 | flags)
+    }
+    fn from_bits(bits: u64, config: &impl CardConfig) -> Result<Self, EncodingError> {
+        if bits.checked_shr(Self::bit_width(config.w())).unwrap_or(0) != 0 {
+            return Err(EncodingError::InvalidBits(bits));
+        }
+        let t = usize::try_from(bits >> 4).map_err(|_| EncodingError::InvalidBits(bits))?;
+        let orientation = Orientation::from_quarter_turns((bits >> 2) as u8);
+        match bits & 0b11 {
+            0b00 => Ok(SimpleCard::Open(check_type(t, config)?, orientation)),
+            0b01 if t == 0 => Ok(SimpleCard::Masked(None, orientation)),
+            0b11 => Ok(SimpleCard::Masked(
+                Some(check_type(t, config)?),
+                orientation,
+            )),
+            _ => Err(EncodingError::InvalidBits(bits)),
+        }
+    }
+}
+impl SimpleCard {
+    /// An upright open card of type `t`.
+    pub fn face_up(t: usize) -> Self {
+        SimpleCard::Open(t, Orientation::Upright)
+    }
+    /// Turns the card face down or face up. A card turned face down by its owner is
+    /// `Masked(Some(t))`, so they still know its type.
+    pub fn flip(self) -> Self {
+        match self {
+            SimpleCard::Open(..) => self.mask(&()),
+            _ => self.open(&()),
+        }
+    }
 }
 
-#[derive(Hash, Debug)]
+/// Identifies a card instance for its whole life, wherever it gets moved to.
+///
+/// Ids are unique within the running app. Shuffled or unpacked cards get fresh ones, so
+/// an id never reveals where a hidden card came from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CardId(u64);
+impl CardId {
+    pub fn fresh() -> Self {
+        CardId(NEXT_CARD_ID.fetch_add(1, Ordering::Relaxed))
+    }
+    /// Makes sure fresh ids never collide with `id`, e.g. one restored from a save.
+    pub(crate) fn reserve(id: CardId) {
+        NEXT_CARD_ID.fetch_max(id.0 + 1, Ordering::Relaxed);
+    }
+}
+static NEXT_CARD_ID: AtomicU64 = AtomicU64::new(0);
+
+/// State attached to one card instance rather than its type, e.g. damage or +1/+1 counters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardAnnotations {
+    /// Named counters, a counter reaching 0 is removed
+    pub counters: BTreeMap<String, i64>,
+    /// Labels without a value
+    pub markers: BTreeSet<String>,
+}
+impl CardAnnotations {
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty() && self.markers.is_empty()
+    }
+    pub fn counter(&self, name: &str) -> i64 {
+        self.counters.get(name).copied().unwrap_or(0)
+    }
+    /// Adds `delta` to the counter `name`, creating it if needed.
+    pub fn add_counter(&mut self, name: impl Into<String>, delta: i64) {
+        let name = name.into();
+        let value = self.counter(&name) + delta;
+        if value == 0 {
+            self.counters.remove(&name);
+        } else {
+            self.counters.insert(name, value);
+        }
+    }
+    pub fn has_marker(&self, marker: &str) -> bool {
+        self.markers.contains(marker)
+    }
+    /// Adds `marker` if it's missing and removes it otherwise.
+    pub fn toggle_marker(&mut self, marker: impl Into<String>) {
+        let marker = marker.into();
+        if !self.markers.remove(&marker) {
+            self.markers.insert(marker);
+        }
+    }
+    /// Text of every badge drawn over the card, counters first.
+    pub fn badges(&self) -> impl Iterator<Item = String> + '_ {
+        self.counters
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .chain(self.markers.iter().cloned())
+    }
+}
+
+#[derive(Hash, Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum SimpleCard {
     /// You are supposed to ensure your card isn't outside its type bounds!
     /// e.g. assert!(t < T)
-    Open(usize),
+    Open(usize, Orientation),
     /// You don't have to specify which type this card *really* is
-    Masked(Option<usize>),
+    Masked(Option<usize>, Orientation),
+}
+
+/// Saves from before orientations hold `Open(t)` and `Masked(t)`, those cards are upright.
+impl<'de> Deserialize<'de> for SimpleCard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(variant_identifier)]
+        enum Variant {
+            Open,
+            Masked,
+        }
+        /// The variant's fields, the orientation may be missing.
+        struct Fields<T>(PhantomData<T>);
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for Fields<T> {
+            type Value = (T, Orientation);
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a card type and an optional orientation")
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let t = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                Ok((t, seq.next_element()?.unwrap_or_default()))
+            }
+        }
+        struct CardVisitor;
+        impl<'de> Visitor<'de> for CardVisitor {
+            type Value = SimpleCard;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "an open or masked card")
+            }
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<SimpleCard, A::Error> {
+                match data.variant()? {
+                    (Variant::Open, fields) => {
+                        let (t, orientation) = fields.tuple_variant(2, Fields(PhantomData))?;
+                        Ok(SimpleCard::Open(t, orientation))
+                    }
+                    (Variant::Masked, fields) => {
+                        let (t, orientation) = fields.tuple_variant(2, Fields(PhantomData))?;
+                        Ok(SimpleCard::Masked(t, orientation))
+                    }
+                }
+            }
+        }
+        deserializer.deserialize_enum("SimpleCard", &["Open", "Masked"], CardVisitor)
+    }
 }
 
-// TODO make CardEncoding be usable as Idx for Trait Index
+/// Clockwise rotation of a card on the table.
+#[derive(Hash, Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    #[default]
+    Upright,
+    /// Turned sideways to the right, e.g. a tapped card
+    Right,
+    UpsideDown,
+    Left,
+}
+impl Orientation {
+    pub fn quarter_turns(self) -> u8 {
+        match self {
+            Orientation::Upright => 0,
+            Orientation::Right => 1,
+            Orientation::UpsideDown => 2,
+            Orientation::Left => 3,
+        }
+    }
+    /// Only the lowest two bits of `turns` matter.
+    pub fn from_quarter_turns(turns: u8) -> Self {
+        match turns % 4 {
+            0 => Orientation::Upright,
+            1 => Orientation::Right,
+            2 => Orientation::UpsideDown,
+            _ => Orientation::Left,
+        }
+    }
+    /// Clockwise angle in radians, as [`Image::rotate`] expects it.
+    pub fn angle(self) -> f32 {
+        self.quarter_turns() as f32 * std::f32::consts::FRAC_PI_2
+    }
+    /// Whether the card's width and height swap on the table.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Orientation::Right | Orientation::Left)
+    }
+    /// Tapping turns an upright card to the right, anything else back upright.
+    pub fn toggle_tapped(self) -> Self {
+        match self {
+            Orientation::Upright => Orientation::Right,
+            _ => Orientation::Upright,
+        }
+    }
+}
+
+/// Everything a [`CardConfig`] knows about one side of a card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardDescriptor {
+    pub name: String,
+    /// URI the image gets loaded from by egui's image loaders
+    pub uri: String,
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Copies of this card in a fresh deck
+    #[serde(default = "CardDescriptor::default_count")]
+    pub count: usize,
+}
+impl CardDescriptor {
+    pub fn new(name: impl Into<String>, uri: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            uri: uri.into(),
+            metadata: BTreeMap::new(),
+            tags: BTreeSet::new(),
+            count: Self::default_count(),
+        }
+    }
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+    fn default_count() -> usize {
+        1
+    }
+    pub fn image(&self) -> Image<'_> {
+        let image = if self.uri == DEFAULT_BACK_URI {
+            Image::from_bytes(DEFAULT_BACK_URI, default_back())
+        } else {
+            Image::new(self.uri.as_str())
+        };
+        image.show_loading_spinner(true).maintain_aspect_ratio(true)
+    }
+}
+
+/// URI of the back used when a [`CardConfig`] has no back image of its own.
+pub const DEFAULT_BACK_URI: &str = "bytes://mcg_visual/default_back.png";
+
+/// A plain back with a diamond pattern, so masked cards never show a face.
+fn default_back() -> &'static [u8] {
+    static BACK: OnceLock<Vec<u8>> = OnceLock::new();
+    BACK.get_or_init(|| {
+        let (width, height) = (200, 300);
+        let back = image::RgbaImage::from_fn(width, height, |x, y| {
+            let border = x.min(y).min(width - 1 - x).min(height - 1 - y);
+            let diamond = ((x + y) / 10 + (x + height - y) / 10) % 2 == 0;
+            match border {
+                0..=3 => image::Rgba([240, 240, 240, 255]),
+                4..=5 => image::Rgba([40, 40, 90, 255]),
+                _ if diamond => image::Rgba([60, 60, 140, 255]),
+                _ => image::Rgba([150, 30, 40, 255]),
+            }
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        back.write_to(&mut png, image::ImageFormat::Png)
+            .expect("Encoding an in-memory PNG can't fail");
+        png.into_inner()
+    })
+}
+
+/// Loads a text file from where the card images come from, see [`assets::source`].
+#[cfg(target_arch = "wasm32")]
+async fn fetch_text(folder: &str, name: &str) -> Result<String, wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast;
+    let source = assets::source();
+    if let Some(bytes) = source.bytes(folder, name) {
+        return Ok(String::from_utf8_lossy(&bytes).into_owned());
+    }
+    let uri = source.uri(folder, name);
+    let window = web_sys::window().ok_or("no window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(&uri))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or_else(|| "response isn't text".into())
+}
+
+fn stem(img_name: &str) -> &str {
+    img_name.rsplit_once('.').map_or(img_name, |(stem, _)| stem)
+}
+
+/// Describes the card types of a game.
+///
+/// Implementations should also implement [`Index`](std::ops::Index) for every
+/// [`CardEncoding`] by forwarding to [`CardConfig::descriptor`], so `config[&card]`
+/// works, see [`DirectoryCardType`].
 #[allow(non_snake_case)]
 pub trait CardConfig {
-    fn img(&self, t: &impl CardEncoding) -> Image;
+    /// The face of type `t`. Panics if `t` isn't below [`CardConfig::T`].
+    fn face(&self, t: usize) -> &CardDescriptor;
+    /// The back, shown for every masked card.
+    fn back(&self) -> &CardDescriptor;
+    /// The side of `card` that's visible.
+    fn descriptor(&self, card: &impl CardEncoding) -> &CardDescriptor {
+        match card.t() {
+            Some(t) if card.is_open() => self.face(t),
+            _ => self.back(),
+        }
+    }
+    fn img(&self, t: &impl CardEncoding) -> Image<'_> {
+        self.descriptor(t).image()
+    }
+    /// Paints `card` into `rect`, turned around its center by the card's [`Orientation`].
+    /// `rect` is the upright card, so sideways cards stick out of it.
+    fn paint(&self, ui: &egui::Ui, card: &impl CardEncoding, rect: egui::Rect) {
+        let orientation = card.orientation();
+        let mut img = self.img(card);
+        if orientation != Orientation::Upright {
+            img = img.rotate(orientation.angle(), Vec2::splat(0.5));
+        }
+        img.paint_at(ui, rect);
+    }
+    /// Copies of type `t` in a fresh deck.
+    fn count(&self, t: usize) -> usize {
+        self.face(t).count
+    }
     fn T(&self) -> usize;
     fn w(&self) -> u32;
     fn natural_size(&self) -> Vec2;
@@ -74,15 +461,11 @@ pub trait CardConfig {
     }
 }
 impl CardConfig for DirectoryCardType {
-    fn img(&self, t: &impl CardEncoding) -> Image {
-        let path = format!(
-            "http://127.0.0.1:8080/media/{folder}/{card}",
-            folder = self.path,
-            card = self.img_names[t.t().unwrap_or(0)]
-        );
-        Image::new(path)
-            .show_loading_spinner(true)
-            .maintain_aspect_ratio(true)
+    fn face(&self, t: usize) -> &CardDescriptor {
+        &self.faces[t]
+    }
+    fn back(&self) -> &CardDescriptor {
+        &self.back
     }
     #[allow(non_snake_case)]
     fn T(&self) -> usize {
@@ -95,83 +478,391 @@ impl CardConfig for DirectoryCardType {
         self.natural_size
     }
 }
+impl<E: CardEncoding> Index<&E> for DirectoryCardType {
+    type Output = CardDescriptor;
 
-#[derive(Clone)]
+    fn index(&self, card: &E) -> &CardDescriptor {
+        self.descriptor(card)
+    }
+}
+
+/// Only the images are saved, the descriptors get resolved again when restored, so they
+/// follow the [`assets::source`] of the running game.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "DirectoryImages")]
 #[allow(non_snake_case)]
 pub struct DirectoryCardType {
     pub(crate) path: String,
     pub(crate) img_names: Vec<String>,
+    back_name: Option<String>,
+    manifest: Option<Manifest>,
+    origin: ImageOrigin,
+    #[serde(skip)]
+    faces: Vec<CardDescriptor>,
+    #[serde(skip)]
+    back: CardDescriptor,
+    #[serde(skip)]
     pub(crate) T: usize,
+    #[serde(skip)]
     pub(crate) w: u32,
     pub(crate) natural_size: Vec2,
 }
+/// The saved part of a [`DirectoryCardType`], older saves' descriptors get ignored.
+#[derive(Deserialize)]
+struct DirectoryImages {
+    path: String,
+    img_names: Vec<String>,
+    back_name: Option<String>,
+    #[serde(default)]
+    manifest: Option<Manifest>,
+    #[serde(default)]
+    origin: ImageOrigin,
+    natural_size: Vec2,
+}
+impl From<DirectoryImages> for DirectoryCardType {
+    fn from(images: DirectoryImages) -> Self {
+        Self::with_back(
+            images.path,
+            images.img_names,
+            images.back_name,
+            images.natural_size,
+            images.manifest,
+            images.origin,
+        )
+    }
+}
 impl DirectoryCardType {
-    /// It's assumed the image URL is inside servers /media directory and the
-    /// type order corresponds to the lexicographical.
+    /// File stem of the image that's used as back by default, compared case-insensitively.
+    pub const BACK_NAME: &'static str = "back";
+
+    /// Opens the browser's directory picker and loads every image inside the picked directory.
     ///
-    /// For real file upload you need to extend the simple python http server to accept uploads.
-    /// Does pythons simple https server already accept POST requests?
+    /// The images get loaded through [`assets::source`] by the directory's path, so the page's
+    /// source has to serve that directory. A `cards.toml` or `cards.json` inside it sets the
+    /// type order, see [`Manifest`], images it doesn't list follow by file name.
     #[cfg(target_arch = "wasm32")]
-    pub fn new_from_selection(holder: Rc<RefCell<Option<DirectoryCardType>>>) {
+    pub fn new_from_selection(holder: Rc<RefCell<Option<DirectoryCardType>>>, toasts: Toasts) {
         let type_rc = Rc::clone(&holder);
         spawn_local(async move {
             let response = JsFuture::from(openDirectoryPicker()).await;
-            if let Ok(file_info_array) = response {
-                let file_info_array: Array = file_info_array.into();
-                let mut path = String::new();
-                let mut img_names = Vec::new();
-                let img_size = Into::<Array>::into(file_info_array.pop())
-                    .to_vec()
-                    .iter()
-                    .map(|x| x.as_f64().unwrap_or(0.0) as f32)
-                    .collect::<Vec<f32>>();
-                let natural_size = egui::vec2(img_size[0], img_size[1]);
-                let file_info_array: Array = file_info_array.pop().into();
-                for file_info in file_info_array {
-                    let file_info: Array = Array::from(&file_info);
-                    let file_info: Vec<String> = file_info
+            match response {
+                Err(err) => toasts.error(format!("Directory selection failed: {err:?}")),
+                Ok(file_info_array) => {
+                    let file_info_array: Array = file_info_array.into();
+                    let mut path = String::new();
+                    let mut img_names = Vec::new();
+                    let mut file_names = Vec::new();
+                    let img_size = Into::<Array>::into(file_info_array.pop())
+                        .to_vec()
                         .iter()
-                        .map(|x| x.as_string().unwrap().clone())
-                        .collect();
-                    let file_name = file_info.first().expect("Every file has a name!").clone();
-                    if path.is_empty() {
-                        path = file_info
-                            .get(1)
-                            .expect("Every file has a path!")
-                            .clone()
-                            .strip_suffix(format!("/{file_name}").as_str())
-                            .unwrap()
-                            .to_string();
+                        .map(|x| x.as_f64().unwrap_or(0.0) as f32)
+                        .collect::<Vec<f32>>();
+                    let [width, height, ..] = img_size[..] else {
+                        toasts.error("Directory picker returned no image size");
+                        return;
+                    };
+                    let natural_size = egui::vec2(width, height);
+                    let file_info_array: Array = file_info_array.pop().into();
+                    for file_info in file_info_array {
+                        let file_info: Array = Array::from(&file_info);
+                        let file_info: Vec<String> = file_info
+                            .iter()
+                            .map(|x| x.as_string().unwrap().clone())
+                            .collect();
+                        let file_name = file_info.first().expect("Every file has a name!").clone();
+                        file_names.push(file_name.clone());
+                        if path.is_empty() {
+                            path = file_info
+                                .get(1)
+                                .expect("Every file has a path!")
+                                .clone()
+                                .strip_suffix(format!("/{file_name}").as_str())
+                                .unwrap()
+                                .to_string();
+                        }
+                        let file_type = file_info.get(2);
+                        if let Some(file_type) = file_type {
+                            if file_type.starts_with("image") {
+                                img_names.push(file_name);
+                            }
+                        }
+                    }
+                    img_names.sort();
+                    if img_names.is_empty() {
+                        toasts.warn(format!("{path} contains no images"));
+                        return;
                     }
-                    let file_type = file_info.get(2);
-                    if let Some(file_type) = file_type {
-                        if file_type.starts_with("image") {
-                            img_names.push(file_name);
+                    let mut manifest = None;
+                    for file_name in Manifest::FILE_NAMES {
+                        if !file_names.iter().any(|name| name == file_name) {
+                            continue;
+                        }
+                        let parsed = match fetch_text(&path, file_name).await {
+                            Ok(text) => {
+                                Manifest::parse(file_name, &text).map_err(|err| err.to_string())
+                            }
+                            Err(err) => Err(format!("{err:?}")),
+                        };
+                        match parsed {
+                            Ok(parsed) => manifest = Some(parsed),
+                            Err(err) => toasts.warn(format!("Ignoring {file_name}: {err}")),
                         }
+                        break;
                     }
+                    let card_type = Self::with_manifest(path, img_names, natural_size, manifest);
+                    for warning in card_type.manifest_warnings() {
+                        toasts.warn(warning);
+                    }
+                    toasts.info(format!(
+                        "Loaded {} cards from {}",
+                        card_type.T, card_type.path
+                    ));
+                    type_rc.borrow_mut().replace(card_type);
                 }
-                img_names.sort();
-                let card_type = Self::new(path, img_names, natural_size);
-                type_rc.borrow_mut().replace(card_type);
             }
         });
     }
-    #[allow(non_snake_case)]
+    /// Opens a native folder dialog and loads every image inside the picked directory.
+    ///
+    /// The images get loaded straight from the filesystem, whatever [`assets::source`] is.
+    /// A `cards.toml` or `cards.json` inside it sets the type order, see [`Manifest`],
+    /// images it doesn't list follow by file name.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_from_selection(holder: Rc<RefCell<Option<DirectoryCardType>>>, toasts: Toasts) {
+        let Some(directory) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+        Self::new_from_path(holder, toasts, &directory);
+    }
+    /// Like [`DirectoryCardType::new_from_selection`] for a known directory, e.g. where no
+    /// folder dialog is available.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_from_path(
+        holder: Rc<RefCell<Option<DirectoryCardType>>>,
+        toasts: Toasts,
+        directory: &Path,
+    ) {
+        match Self::load_directory(directory) {
+            Ok((card_type, warnings)) => {
+                for warning in warnings {
+                    toasts.warn(warning);
+                }
+                toasts.info(format!(
+                    "Loaded {} cards from {}",
+                    card_type.T, card_type.path
+                ));
+                holder.borrow_mut().replace(card_type);
+            }
+            Err(err) => toasts.error(format!("Failed to load {}: {err}", directory.display())),
+        }
+    }
+    /// Collects all images inside `directory` that can be decoded, their size is taken from
+    /// the first one. A [`Manifest`] inside the directory gets applied to them, a broken one
+    /// is ignored like on the web.
+    ///
+    /// The images are loaded through `file://` URIs, whatever [`assets::source`] is.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_from_directory(directory: &Path) -> std::io::Result<Self> {
+        let (card_type, warnings) = Self::load_directory(directory)?;
+        for warning in warnings {
+            crate::sprintln!("{warning}");
+        }
+        Ok(card_type)
+    }
+    /// [`DirectoryCardType::new_from_directory`] together with what the user should know
+    /// about the manifest.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_directory(directory: &Path) -> std::io::Result<(Self, Vec<String>)> {
+        let mut images = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let readable = image::ImageFormat::from_path(entry.path())
+                .is_ok_and(|format| format.reading_enabled());
+            if !entry.file_type()?.is_file() || !readable {
+                continue;
+            }
+            // Broken images couldn't be shown anyway
+            if let Ok(dimensions) = image::image_dimensions(entry.path()) {
+                images.push((entry.file_name().to_string_lossy().into_owned(), dimensions));
+            }
+        }
+        images.sort();
+        let (_, (width, height)) = images.first().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "directory contains no images")
+        })?;
+        let natural_size = egui::vec2(*width as f32, *height as f32);
+        let img_names = images.into_iter().map(|(name, _)| name).collect();
+        let mut manifest = None;
+        let mut warnings = Vec::new();
+        for file_name in Manifest::FILE_NAMES {
+            let file = directory.join(file_name);
+            if !file.is_file() {
+                continue;
+            }
+            let parsed = std::fs::read_to_string(file)
+                .map_err(|err| err.to_string())
+                .and_then(|text| Manifest::parse(file_name, &text).map_err(|err| err.to_string()));
+            match parsed {
+                Ok(parsed) => manifest = Some(parsed),
+                Err(err) => warnings.push(format!("Ignoring {file_name}: {err}")),
+            }
+            break;
+        }
+        let path = directory.to_string_lossy().into_owned();
+        let card_type = Self::assemble(path, img_names, natural_size, manifest, ImageOrigin::Local);
+        warnings.extend(card_type.manifest_warnings());
+        Ok((card_type, warnings))
+    }
+    /// An image named like [`DirectoryCardType::BACK_NAME`], e.g. `back.png`, becomes the
+    /// back instead of a card type.
     pub fn new(path: String, img_names: Vec<String>, natural_size: Vec2) -> Self {
+        Self::with_manifest(path, img_names, natural_size, None)
+    }
+    /// Like [`DirectoryCardType::new`], but names, order, tags, counts, properties and the
+    /// back image are taken from `manifest` where it has them.
+    pub fn with_manifest(
+        path: String,
+        img_names: Vec<String>,
+        natural_size: Vec2,
+        manifest: Option<Manifest>,
+    ) -> Self {
+        Self::assemble(path, img_names, natural_size, manifest, ImageOrigin::Source)
+    }
+    pub(crate) fn assemble(
+        path: String,
+        mut img_names: Vec<String>,
+        natural_size: Vec2,
+        manifest: Option<Manifest>,
+        origin: ImageOrigin,
+    ) -> Self {
+        let back = manifest
+            .as_ref()
+            .and_then(|manifest| manifest.back.as_ref());
+        let back_name = img_names
+            .iter()
+            .position(|name| match back {
+                Some(back) => name == back,
+                None => stem(name).eq_ignore_ascii_case(Self::BACK_NAME),
+            })
+            .map(|idx| img_names.remove(idx));
+        Self::with_back(path, img_names, back_name, natural_size, manifest, origin)
+    }
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+    /// Where the manifest doesn't match the images, e.g. a back that isn't there.
+    pub fn manifest_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let back = self.manifest.as_ref().and_then(|m| m.back.as_deref());
+        if let Some(back) = back.filter(|back| self.back_name.as_deref() != Some(*back)) {
+            warnings.push(format!(
+                "Back image {back} of the manifest is missing, using the default back"
+            ));
+        }
+        warnings
+    }
+    /// Makes `img_name` the back, or the built-in one for `None`. The previous back image
+    /// becomes a card type again, `img_name` stops being one.
+    pub fn set_back(&mut self, img_name: Option<String>) {
+        let mut img_names = std::mem::take(&mut self.img_names);
+        img_names.extend(self.back_name.take());
+        img_names.retain(|name| Some(name) != img_name.as_ref());
+        let path = std::mem::take(&mut self.path);
+        let manifest = self.manifest.take();
+        *self = Self::with_back(
+            path,
+            img_names,
+            img_name,
+            self.natural_size,
+            manifest,
+            self.origin,
+        );
+    }
+    /// The image used as back, `None` for the built-in one.
+    pub fn back_image(&self) -> Option<&str> {
+        self.back_name.as_deref()
+    }
+    #[allow(non_snake_case)]
+    fn with_back(
+        path: String,
+        mut img_names: Vec<String>,
+        back_name: Option<String>,
+        natural_size: Vec2,
+        manifest: Option<Manifest>,
+        origin: ImageOrigin,
+    ) -> Self {
+        let entry = |name: &str| manifest.as_ref().and_then(|manifest| manifest.entry(name));
+        img_names.sort_by(|a, b| {
+            let key = |name: &String| {
+                let order = entry(name).map(|entry| entry.order.unwrap_or(i64::MAX));
+                (order.is_none(), order)
+            };
+            key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+        });
         let T = img_names.len();
         let w = T.next_power_of_two().ilog2();
+        let faces: Vec<CardDescriptor> = img_names
+            .iter()
+            .map(|name| {
+                let mut face = CardDescriptor::new(stem(name), Self::uri(&path, name, origin));
+                if let Some(entry) = entry(name) {
+                    if let Some(name) = &entry.name {
+                        face.name = name.clone();
+                    }
+                    face.tags = entry.tags.clone();
+                    face.count = entry.count;
+                    face.metadata = entry
+                        .properties
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.to_string()))
+                        .collect();
+                }
+                face
+            })
+            .collect();
+        let back = match &back_name {
+            Some(name) => CardDescriptor::new("Back", Self::uri(&path, name, origin)),
+            None => CardDescriptor::new("Back", DEFAULT_BACK_URI),
+        };
         Self {
             path,
             img_names,
+            back_name,
+            manifest,
+            origin,
+            faces,
+            back,
             T,
             w,
             natural_size,
         }
     }
-    pub fn all_images(&self) -> Iter<String> {
+    fn uri(path: &str, img_name: &str, origin: ImageOrigin) -> String {
+        match origin {
+            ImageOrigin::Source => assets::source().uri(path, img_name),
+            #[cfg(not(target_arch = "wasm32"))]
+            ImageOrigin::Local => format!("file://{}", Path::new(path).join(img_name).display()),
+            #[cfg(target_arch = "wasm32")]
+            ImageOrigin::Local => assets::source().uri(path, img_name),
+            ImageOrigin::Embedded => format!("bytes://{path}/{img_name}"),
+        }
+    }
+    pub fn all_images(&self) -> Iter<'_, String> {
         self.img_names.iter()
     }
 }
+/// Where the images of a [`DirectoryCardType`] get loaded from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ImageOrigin {
+    /// Through [`assets::source`]
+    #[default]
+    Source,
+    /// From the local filesystem, whatever [`assets::source`] is
+    Local,
+    /// From an [`EmbeddedCardSet`](embedded::EmbeddedCardSet) compiled into the binary
+    Embedded,
+}
+
 impl Debug for DirectoryCardType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DirectoryCardType")
@@ -181,3 +872,118 @@ impl Debug for DirectoryCardType {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn cards_without_orientation_deserialize_upright() {
+        let old: Vec<SimpleCard> =
+            ron::from_str("[Open(1), Masked(Some(0)), Masked(None)]").unwrap();
+        assert_eq!(
+            old,
+            vec![
+                SimpleCard::face_up(1),
+                SimpleCard::Masked(Some(0), Orientation::Upright),
+                SimpleCard::Masked(None, Orientation::Upright),
+            ]
+        );
+        let cards = vec![
+            SimpleCard::Open(4, Orientation::Left),
+            SimpleCard::Masked(None, Orientation::Right),
+        ];
+        let ron = ron::to_string(&cards).unwrap();
+        assert_eq!(ron::from_str::<Vec<SimpleCard>>(&ron).unwrap(), cards);
+        let json = serde_json::to_string(&cards).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<SimpleCard>>(&json).unwrap(),
+            cards
+        );
+        assert!(ron::from_str::<SimpleCard>("Open()").is_err());
+        assert!(ron::from_str::<SimpleCard>("Tapped(1)").is_err());
+    }
+
+    #[test]
+    fn manifest_orders_and_names_types() {
+        let manifest = Manifest::parse(
+            "cards.toml",
+            r#"
+back = "cover.png"
+[[cards]]
+image = "b.png"
+name = "Bee"
+order = 0
+"#,
+        )
+        .unwrap();
+        let images = names(&["a.png", "b.png", "cover.png"]);
+        let config = DirectoryCardType::with_manifest(
+            "set".into(),
+            images,
+            Vec2::splat(1.0),
+            Some(manifest),
+        );
+        assert_eq!(config.T(), 2);
+        assert_eq!(config.face(0).name, "Bee");
+        assert_eq!(config.face(1).name, "a");
+        assert_eq!(config.back_image(), Some("cover.png"));
+        assert!(config.manifest_warnings().is_empty());
+    }
+
+    #[test]
+    fn missing_manifest_back_gets_reported() {
+        let manifest = Manifest::parse("cards.toml", r#"back = "cover.png""#).unwrap();
+        let images = names(&["a.png", "back.png"]);
+        let config = DirectoryCardType::with_manifest(
+            "set".into(),
+            images,
+            Vec2::splat(1.0),
+            Some(manifest),
+        );
+        // The manifest's back wins over the naming convention, even if it's missing
+        assert_eq!(config.back_image(), None);
+        assert_eq!(config.T(), 2);
+        assert_eq!(config.back().uri, DEFAULT_BACK_URI);
+        assert_eq!(config.manifest_warnings().len(), 1);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn broken_manifest_is_ignored_with_a_warning() {
+        let directory =
+            std::env::temp_dir().join(format!("mcg_visual_broken_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let image = image::RgbaImage::new(3, 5);
+        image.save(directory.join("a.png")).unwrap();
+        image.save(directory.join("b.png")).unwrap();
+        std::fs::write(directory.join("cards.toml"), "cards = [").unwrap();
+        let loaded = DirectoryCardType::load_directory(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        let (config, warnings) = loaded.unwrap();
+        assert_eq!(config.T(), 2);
+        assert_eq!(config.natural_size(), egui::vec2(3.0, 5.0));
+        assert!(config.manifest().is_none());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Ignoring cards.toml"));
+    }
+
+    #[test]
+    fn restored_types_resolve_their_uris_again() {
+        let saved = r#"(path:"set",img_names:["b.png","a.png"],back_name:Some("back.png"),manifest:None,origin:Embedded,faces:[(name:"b",uri:"file://stale/b.png",metadata:{},tags:[],count:1)],back:(name:"Back",uri:"file://stale/back.png",metadata:{},tags:[],count:1),T:1,w:0,natural_size:(x:2.0,y:3.0))"#;
+        let restored: DirectoryCardType = ron::from_str(saved).unwrap();
+        assert_eq!(restored.T(), 2);
+        assert_eq!(restored.face(0).uri, "bytes://set/a.png");
+        assert_eq!(restored.face(1).uri, "bytes://set/b.png");
+        assert_eq!(restored.back().uri, "bytes://set/back.png");
+        let ron = ron::to_string(&restored).unwrap();
+        assert!(!ron.contains("uri"));
+        let again: DirectoryCardType = ron::from_str(&ron).unwrap();
+        assert_eq!(again.face(1).uri, restored.face(1).uri);
+        assert_eq!(again.natural_size(), restored.natural_size());
+    }
+}
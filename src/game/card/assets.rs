@@ -0,0 +1,176 @@
+//! Where card images come from.
+//!
+//! [`CardConfig`](crate::game::card::CardConfig) implementations turn a folder and a file
+//! name into a URI through the [`source`] chosen at startup, see
+//! [`start_game`](crate::start_game) and [`run_native`](crate::run_native).
+use egui::load::Bytes;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub trait AssetSource: Debug + Send + Sync {
+    /// URI egui's image loaders load `name` inside `folder` from.
+    fn uri(&self, folder: &str, name: &str) -> String;
+    /// The raw file, if the source holds it in memory.
+    fn bytes(&self, _folder: &str, _name: &str) -> Option<Arc<[u8]>> {
+        None
+    }
+    /// Makes the files known to egui, called once its context exists.
+    fn install(&self, _ctx: &egui::Context) {}
+}
+
+/// Images served over HTTP, e.g. by the server the web build comes from.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    base: String,
+}
+impl HttpSource {
+    /// `base` is either an absolute URL or a path relative to the page's origin, e.g. `/media`.
+    pub fn new(base: impl Into<String>) -> Self {
+        let base = base.into();
+        let base = base.trim_end_matches('/');
+        let base = if base.contains("://") || base.starts_with('/') {
+            base.to_string()
+        } else {
+            format!("/{base}")
+        };
+        Self { base }
+    }
+    fn origin() -> Option<String> {
+        #[cfg(target_arch = "wasm32")]
+        return web_sys::window()?.location().origin().ok();
+        #[cfg(not(target_arch = "wasm32"))]
+        return None;
+    }
+}
+/// Everything but the unreserved characters of RFC 3986 gets escaped inside a path segment.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+impl AssetSource for HttpSource {
+    /// The segments of `folder` and `name` get percent-encoded, the `/` between them stay.
+    fn uri(&self, folder: &str, name: &str) -> String {
+        let folder: Vec<String> = folder
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+            .collect();
+        let path = format!(
+            "{}/{}",
+            folder.join("/"),
+            utf8_percent_encode(name, SEGMENT)
+        );
+        if self.base.contains("://") {
+            format!("{}/{path}", self.base)
+        } else {
+            let origin = Self::origin().unwrap_or_default();
+            format!("{origin}{}/{path}", self.base)
+        }
+    }
+}
+
+/// Images on the local filesystem, folders are relative to `root` unless they're absolute.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default)]
+pub struct FileSource {
+    root: PathBuf,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl FileSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl AssetSource for FileSource {
+    fn uri(&self, folder: &str, name: &str) -> String {
+        format!("file://{}", self.root.join(folder).join(name).display())
+    }
+}
+
+/// Images kept in memory, egui serves them under `bytes://{folder}/{name}`.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: BTreeMap<String, Arc<[u8]>>,
+}
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_file(mut self, folder: &str, name: &str, bytes: impl Into<Arc<[u8]>>) -> Self {
+        self.insert(folder, name, bytes);
+        self
+    }
+    pub fn insert(&mut self, folder: &str, name: &str, bytes: impl Into<Arc<[u8]>>) {
+        self.files.insert(self.uri(folder, name), bytes.into());
+    }
+    /// Names of the files inside `folder`.
+    pub fn files(&self, folder: &str) -> Vec<String> {
+        let prefix = self.uri(folder, "");
+        self.files
+            .keys()
+            .filter_map(|uri| uri.strip_prefix(&prefix))
+            .filter(|name| !name.contains('/'))
+            .map(str::to_string)
+            .collect()
+    }
+}
+impl AssetSource for MemorySource {
+    fn uri(&self, folder: &str, name: &str) -> String {
+        format!("bytes://{folder}/{name}")
+    }
+    fn bytes(&self, folder: &str, name: &str) -> Option<Arc<[u8]>> {
+        self.files.get(&self.uri(folder, name)).cloned()
+    }
+    fn install(&self, ctx: &egui::Context) {
+        for (uri, bytes) in &self.files {
+            ctx.include_bytes(uri.clone(), Bytes::Shared(Arc::clone(bytes)));
+        }
+    }
+}
+
+static SOURCE: RwLock<Option<Arc<dyn AssetSource>>> = RwLock::new(None);
+
+/// Replaces the source card images get resolved through. Configs created before keep the
+/// URIs they resolved already, until they get saved and restored.
+pub fn set_source(source: impl AssetSource + 'static) {
+    *SOURCE.write().expect("Asset source lock poisoned") = Some(Arc::new(source));
+}
+/// The source set by [`set_source`], or [`default_source`] if there is none.
+pub fn source() -> Arc<dyn AssetSource> {
+    SOURCE
+        .read()
+        .expect("Asset source lock poisoned")
+        .clone()
+        .unwrap_or_else(default_source)
+}
+/// `/media` on the page's origin for the web build, the working directory natively.
+pub fn default_source() -> Arc<dyn AssetSource> {
+    #[cfg(target_arch = "wasm32")]
+    return Arc::new(HttpSource::new("/media"));
+    #[cfg(not(target_arch = "wasm32"))]
+    return Arc::new(FileSource::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_uris_escape_every_segment() {
+        let source = HttpSource::new("https://cards.example/media/");
+        assert_eq!(
+            source.uri("my decks/Skat #1", "Ober?.png"),
+            "https://cards.example/media/my%20decks/Skat%20%231/Ober%3F.png"
+        );
+        assert_eq!(
+            source.uri("set", "ä.png"),
+            "https://cards.example/media/set/%C3%A4.png"
+        );
+        assert_eq!(HttpSource::new("media").uri("a b", "c"), "/media/a%20b/c");
+    }
+}
@@ -0,0 +1,280 @@
+//! Card sets shipped as one image holding all faces, e.g. in a grid.
+use crate::game::card::{CardConfig, CardDescriptor, CardEncoding, DEFAULT_BACK_URI};
+use egui::{Image, Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::ops::Index;
+
+/// A [`CardConfig`] cutting every side out of a single atlas image.
+///
+/// Cells are pixel rects inside the atlas, type `t` is the `t`-th cell. Without a back cell
+/// the built-in back is used. An atlas size without area is taken from the cells instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct AtlasCardType {
+    /// URI of the atlas, e.g. from [`assets::source`](crate::game::card::assets::source)
+    uri: String,
+    /// Size of the whole atlas in pixels
+    atlas_size: Vec2,
+    cells: Vec<Cell>,
+    back_cell: Option<Rect>,
+    faces: Vec<CardDescriptor>,
+    back: CardDescriptor,
+    T: usize,
+    w: u32,
+    natural_size: Vec2,
+}
+/// A card type's rect, named once [`AtlasCardType::names`] got called.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Cell {
+    rect: Rect,
+    name: Option<String>,
+}
+
+/// Builder
+impl AtlasCardType {
+    /// `columns` times `rows` cells of equal size filling the atlas, counted row by row.
+    pub fn grid(uri: impl Into<String>, atlas_size: Vec2, columns: usize, rows: usize) -> Self {
+        let cell = atlas_size / Vec2::new(columns.max(1) as f32, rows.max(1) as f32);
+        let cells = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let min = Pos2::new(column as f32 * cell.x, row as f32 * cell.y);
+                Rect::from_min_size(min, cell)
+            })
+            .collect();
+        Self::from_rects(uri, atlas_size, cells)
+    }
+    /// One card type per rect, in the given order.
+    pub fn from_rects(uri: impl Into<String>, atlas_size: Vec2, cells: Vec<Rect>) -> Self {
+        let mut atlas = Self {
+            uri: uri.into(),
+            atlas_size,
+            cells: cells
+                .into_iter()
+                .map(|rect| Cell { rect, name: None })
+                .collect(),
+            back_cell: None,
+            faces: vec![],
+            back: CardDescriptor::new("Back", DEFAULT_BACK_URI),
+            T: 0,
+            w: 0,
+            natural_size: Vec2::ZERO,
+        };
+        atlas.rebuild();
+        atlas
+    }
+    /// Makes cell `idx` the back, it stops being a card type. Ignored if there is no such
+    /// cell.
+    pub fn back_cell(mut self, idx: usize) -> Self {
+        if idx < self.cells.len() {
+            self.back_cell = Some(self.cells.remove(idx).rect);
+            self.rebuild();
+        }
+        self
+    }
+    /// Takes the back from `rect`, which doesn't have to be one of the cells.
+    pub fn back_rect(mut self, rect: Rect) -> Self {
+        self.back_cell = Some(rect);
+        self.rebuild();
+        self
+    }
+    /// Keeps only the first `count` cells, e.g. to drop empty ones at the end of a grid.
+    pub fn face_count(mut self, count: usize) -> Self {
+        self.cells.truncate(count);
+        self.rebuild();
+        self
+    }
+    /// Names the current cells in order, the remaining ones keep `Card {t}`. Names stay with
+    /// their cell if it becomes the back or a later cell.
+    pub fn names(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        for (cell, name) in self.cells.iter_mut().zip(names) {
+            cell.name = Some(name.into());
+        }
+        self.rebuild();
+        self
+    }
+}
+/// Getter
+impl AtlasCardType {
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+    /// The pixel rect of type `t` inside the atlas.
+    pub fn cell(&self, t: usize) -> Option<Rect> {
+        self.cells.get(t).map(|cell| cell.rect)
+    }
+    pub fn back_cell_rect(&self) -> Option<Rect> {
+        self.back_cell
+    }
+}
+/// Internal
+impl AtlasCardType {
+    #[allow(non_snake_case)]
+    fn rebuild(&mut self) {
+        let T = self.cells.len();
+        if !has_area(self.atlas_size) {
+            // Where the cells end is the best guess for the atlas' size
+            self.atlas_size = self
+                .cells
+                .iter()
+                .map(|cell| cell.rect)
+                .chain(self.back_cell)
+                .fold(Vec2::ZERO, |size, rect| size.max(rect.max.to_vec2()));
+        }
+        self.faces = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(t, cell)| {
+                let name = cell.name.clone().unwrap_or_else(|| format!("Card {t}"));
+                CardDescriptor::new(name, self.uri.as_str())
+            })
+            .collect();
+        self.back = match self.back_cell {
+            Some(_) => CardDescriptor::new("Back", self.uri.as_str()),
+            None => CardDescriptor::new("Back", DEFAULT_BACK_URI),
+        };
+        self.T = T;
+        self.w = T.next_power_of_two().ilog2();
+        self.natural_size = self
+            .cells
+            .first()
+            .map(|cell| cell.rect)
+            .or(self.back_cell)
+            .map_or(self.atlas_size, |rect| rect.size());
+    }
+    /// The cell `card` shows, [`None`] for the built-in back.
+    fn visible_cell(&self, card: &impl CardEncoding) -> Option<Rect> {
+        match card.t() {
+            Some(t) if card.is_open() => self.cell(t),
+            _ => self.back_cell,
+        }
+    }
+    /// `rect` in texture coordinates, from 0 to 1. [`None`] if there's no atlas to map to.
+    fn uv(&self, rect: Rect) -> Option<Rect> {
+        has_area(self.atlas_size).then(|| {
+            Rect::from_min_max(
+                (rect.min.to_vec2() / self.atlas_size).to_pos2(),
+                (rect.max.to_vec2() / self.atlas_size).to_pos2(),
+            )
+        })
+    }
+}
+impl CardConfig for AtlasCardType {
+    fn face(&self, t: usize) -> &CardDescriptor {
+        &self.faces[t]
+    }
+    fn back(&self) -> &CardDescriptor {
+        &self.back
+    }
+    fn img(&self, card: &impl CardEncoding) -> Image<'_> {
+        let img = self.descriptor(card).image();
+        match self.visible_cell(card).and_then(|cell| self.uv(cell)) {
+            Some(uv) => img.uv(uv),
+            None => img,
+        }
+    }
+    #[allow(non_snake_case)]
+    fn T(&self) -> usize {
+        self.T
+    }
+    fn w(&self) -> u32 {
+        self.w
+    }
+    fn natural_size(&self) -> Vec2 {
+        self.natural_size
+    }
+    /// The atlas texture has the size of all cells, so the image is sized by the cell.
+    fn draw_at(
+        &self,
+        ui: &mut egui::Ui,
+        card: &impl CardEncoding,
+        pos: Pos2,
+    ) -> egui::InnerResponse<egui::Response> {
+        let size = self
+            .visible_cell(card)
+            .map_or(self.natural_size, |cell| cell.size());
+        let area = egui::Area::new(ui.next_auto_id()).current_pos(pos);
+        area.show(ui.ctx(), |ui| {
+            ui.add(self.img(card).fit_to_exact_size(size))
+        })
+    }
+}
+fn has_area(size: Vec2) -> bool {
+    size.x > 0.0 && size.y > 0.0 && size.is_finite()
+}
+
+impl<E: CardEncoding> Index<&E> for AtlasCardType {
+    type Output = CardDescriptor;
+
+    fn index(&self, card: &E) -> &CardDescriptor {
+        self.descriptor(card)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::SimpleCard;
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn grid_cells_fill_the_atlas_row_by_row() {
+        let atlas = AtlasCardType::grid("bytes://atlas.png", vec2(300.0, 200.0), 3, 2);
+        assert_eq!(atlas.T(), 6);
+        assert_eq!(atlas.natural_size(), vec2(100.0, 100.0));
+        assert_eq!(
+            atlas.cell(1),
+            Some(Rect::from_min_size(pos2(100.0, 0.0), vec2(100.0, 100.0)))
+        );
+        assert_eq!(
+            atlas.cell(5),
+            Some(Rect::from_min_size(pos2(200.0, 100.0), vec2(100.0, 100.0)))
+        );
+        assert_eq!(atlas.cell(6), None);
+    }
+
+    #[test]
+    fn uvs_are_relative_to_the_atlas() {
+        let atlas = AtlasCardType::grid("bytes://atlas.png", vec2(400.0, 200.0), 4, 2);
+        let uv = atlas.uv(atlas.cell(6).unwrap()).unwrap();
+        assert_eq!(uv, Rect::from_min_max(pos2(0.5, 0.5), pos2(0.75, 1.0)));
+        let back = atlas.back_cell(7);
+        let uv = back.uv(back.back_cell_rect().unwrap()).unwrap();
+        assert_eq!(uv, Rect::from_min_max(pos2(0.75, 0.5), pos2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn atlas_without_area_never_gives_nan() {
+        let cells = vec![
+            Rect::from_min_size(pos2(0.0, 0.0), vec2(50.0, 70.0)),
+            Rect::from_min_size(pos2(50.0, 0.0), vec2(50.0, 70.0)),
+        ];
+        // The size is taken from the cells
+        let atlas = AtlasCardType::from_rects("bytes://atlas.png", Vec2::ZERO, cells);
+        let uv = atlas.uv(atlas.cell(1).unwrap()).unwrap();
+        assert_eq!(uv, Rect::from_min_max(pos2(0.5, 0.0), pos2(1.0, 1.0)));
+        // Nothing to take it from
+        let atlas = AtlasCardType::grid("bytes://atlas.png", Vec2::ZERO, 2, 2);
+        assert_eq!(atlas.uv(atlas.cell(0).unwrap()), None);
+        assert!(!atlas.natural_size().any_nan());
+        let _ = atlas.img(&SimpleCard::face_up(0));
+        let atlas = AtlasCardType::grid("bytes://atlas.png", vec2(f32::NAN, 10.0), 1, 1);
+        assert_eq!(atlas.uv(atlas.cell(0).unwrap()), None);
+    }
+
+    #[test]
+    fn names_stay_with_their_cells() {
+        let atlas = AtlasCardType::grid("bytes://atlas.png", vec2(300.0, 100.0), 3, 1)
+            .names(["Back", "One", "Two"])
+            .back_cell(0);
+        assert_eq!(atlas.T(), 2);
+        assert_eq!(atlas.face(0).name, "One");
+        assert_eq!(atlas.face(1).name, "Two");
+        let atlas = AtlasCardType::grid("bytes://atlas.png", vec2(300.0, 100.0), 3, 1)
+            .names(["Zero"])
+            .face_count(2);
+        assert_eq!(atlas.face(0).name, "Zero");
+        assert_eq!(atlas.face(1).name, "Card 1");
+    }
+}
@@ -0,0 +1,355 @@
+//! Compact byte representation of card collections, e.g. decks, hands or move logs.
+//!
+//! Every card takes [`CardEncoding::bit_width`] bits for the width [`CardConfig::w`] of
+//! its config. A packed collection starts with its card count as little endian `u32`,
+//! followed by the cards' bits, least significant bit first, padded to a full byte.
+use crate::game::card::{CardConfig, CardEncoding};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(non_snake_case)]
+pub enum EncodingError {
+    /// The card's type isn't below [`CardConfig::T`]
+    OutOfBounds { t: usize, T: usize },
+    /// The bits don't describe any card
+    InvalidBits(u64),
+    /// The cards need more than 64 bits each
+    TooWide(u32),
+    /// The cards need no bits at all, so a packed count couldn't be checked
+    ZeroWidth,
+    /// The card can't be represented in [`CardEncoding::bit_width`] bits
+    Unpackable,
+    /// The packed bytes are shorter or longer than their card count says
+    Length { expected: usize, actual: usize },
+}
+impl Display for EncodingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::OutOfBounds { t, T } => write!(f, "card type {t} is out of 0..{T}"),
+            EncodingError::InvalidBits(bits) => write!(f, "{bits:#b} is no valid card"),
+            EncodingError::TooWide(width) => write!(f, "{width} bits per card exceed 64"),
+            EncodingError::ZeroWidth => write!(f, "cards without any bits can't be packed"),
+            EncodingError::Unpackable => write!(f, "card can't be bit-packed"),
+            EncodingError::Length { expected, actual } => {
+                write!(f, "expected {expected} bytes, got {actual}")
+            }
+        }
+    }
+}
+impl std::error::Error for EncodingError {}
+
+/// Fails unless `t` is a type of `config`.
+pub fn check_type(t: usize, config: &impl CardConfig) -> Result<usize, EncodingError> {
+    if t < config.T() {
+        Ok(t)
+    } else {
+        Err(EncodingError::OutOfBounds { t, T: config.T() })
+    }
+}
+
+/// Fails unless `bits` fit into the lowest `width` bits.
+pub fn check_width(bits: u64, width: u32) -> Result<u64, EncodingError> {
+    if bits.checked_shr(width).unwrap_or(0) == 0 {
+        Ok(bits)
+    } else {
+        Err(EncodingError::Unpackable)
+    }
+}
+
+/// Packs `cards` into bytes, see the [module docs](self) for the layout.
+pub fn pack<E: CardEncoding>(
+    cards: &[E],
+    config: &impl CardConfig,
+) -> Result<Vec<u8>, EncodingError> {
+    let width = card_width::<E>(config)?;
+    let count = u32::try_from(cards.len()).map_err(|_| EncodingError::Length {
+        expected: u32::MAX as usize,
+        actual: cards.len(),
+    })?;
+    let mut bytes = Vec::with_capacity(packed_len(cards.len(), width)?);
+    bytes.extend_from_slice(&count.to_le_bytes());
+    let mut buffer: u128 = 0;
+    let mut buffered = 0;
+    for card in cards {
+        buffer |= (check_width(card.to_bits(config)?, width)? as u128) << buffered;
+        buffered += width;
+        while buffered >= 8 {
+            bytes.push(buffer as u8);
+            buffer >>= 8;
+            buffered -= 8;
+        }
+    }
+    if buffered > 0 {
+        bytes.push(buffer as u8);
+    }
+    Ok(bytes)
+}
+
+/// Reverses [`pack`], validating every card against `config`.
+pub fn unpack<E: CardEncoding>(
+    bytes: &[u8],
+    config: &impl CardConfig,
+) -> Result<Vec<E>, EncodingError> {
+    let width = card_width::<E>(config)?;
+    let Some((count, mut rest)) = bytes.split_first_chunk::<4>() else {
+        return Err(EncodingError::Length {
+            expected: 4,
+            actual: bytes.len(),
+        });
+    };
+    let count = u32::from_le_bytes(*count) as usize;
+    let expected = packed_len(count, width)?;
+    if bytes.len() != expected {
+        return Err(EncodingError::Length {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    let mask = if width == 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    };
+    let mut cards = Vec::with_capacity(count);
+    let mut buffer: u128 = 0;
+    let mut buffered = 0;
+    for _ in 0..count {
+        while buffered < width {
+            buffer |= (rest[0] as u128) << buffered;
+            rest = &rest[1..];
+            buffered += 8;
+        }
+        cards.push(E::from_bits(buffer as u64 & mask, config)?);
+        buffer >>= width;
+        buffered -= width;
+    }
+    Ok(cards)
+}
+
+fn card_width<E: CardEncoding>(config: &impl CardConfig) -> Result<u32, EncodingError> {
+    match E::bit_width(config.w()) {
+        0 => Err(EncodingError::ZeroWidth),
+        width if width > 64 => Err(EncodingError::TooWide(width)),
+        width => Ok(width),
+    }
+}
+
+/// Fails for counts whose bytes wouldn't fit into memory anyway, e.g. read from broken
+/// input on 32-bit targets.
+fn packed_len(count: usize, width: u32) -> Result<usize, EncodingError> {
+    count
+        .checked_mul(width as usize)
+        .and_then(|bits| bits.div_ceil(8).checked_add(4))
+        .ok_or(EncodingError::Length {
+            expected: usize::MAX,
+            actual: count,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::{CardDescriptor, Orientation, SimpleCard};
+    use egui::Vec2;
+
+    /// A config with any number of types and any width, even inconsistent ones.
+    #[allow(non_snake_case)]
+    struct TestConfig {
+        T: usize,
+        w: u32,
+        side: CardDescriptor,
+    }
+    impl TestConfig {
+        #[allow(non_snake_case)]
+        fn new(T: usize, w: u32) -> Self {
+            Self {
+                T,
+                w,
+                side: CardDescriptor::new("Card", "bytes://test/card.png"),
+            }
+        }
+    }
+    impl CardConfig for TestConfig {
+        fn face(&self, _t: usize) -> &CardDescriptor {
+            &self.side
+        }
+        fn back(&self) -> &CardDescriptor {
+            &self.side
+        }
+        fn T(&self) -> usize {
+            self.T
+        }
+        fn w(&self) -> u32 {
+            self.w
+        }
+        fn natural_size(&self) -> Vec2 {
+            Vec2::splat(1.0)
+        }
+    }
+
+    /// An encoding without any bits, nothing about the card is stored.
+    struct Blank;
+    impl CardEncoding for Blank {
+        type MaskKey = ();
+        type OpenKey = ();
+
+        fn t(&self) -> Option<usize> {
+            None
+        }
+        fn is_masked(&self) -> bool {
+            true
+        }
+        fn mask(self, _key: &()) -> Self {
+            self
+        }
+        fn open(self, _key: &()) -> Self {
+            self
+        }
+        fn bit_width(_w: u32) -> u32 {
+            0
+        }
+        fn to_bits(&self, _config: &impl CardConfig) -> Result<u64, EncodingError> {
+            Ok(0)
+        }
+        fn from_bits(_bits: u64, _config: &impl CardConfig) -> Result<Self, EncodingError> {
+            Ok(Blank)
+        }
+    }
+
+    fn all_cards(config: &impl CardConfig) -> Vec<SimpleCard> {
+        let orientations = [
+            Orientation::Upright,
+            Orientation::Right,
+            Orientation::UpsideDown,
+            Orientation::Left,
+        ];
+        orientations
+            .iter()
+            .flat_map(|&orientation| {
+                (0..config.T())
+                    .flat_map(move |t| {
+                        [
+                            SimpleCard::Open(t, orientation),
+                            SimpleCard::Masked(Some(t), orientation),
+                        ]
+                    })
+                    .chain([SimpleCard::Masked(None, orientation)])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let config = TestConfig::new(52, 6);
+        for card in all_cards(&config) {
+            let bits = card.to_bits(&config).unwrap();
+            assert_eq!(check_width(bits, SimpleCard::bit_width(6)), Ok(bits));
+            assert_eq!(SimpleCard::from_bits(bits, &config), Ok(card));
+        }
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let config = TestConfig::new(52, 6);
+        let cards = all_cards(&config);
+        let bytes = pack(&cards, &config).unwrap();
+        assert_eq!(bytes.len(), packed_len(cards.len(), 10).unwrap());
+        assert_eq!(unpack::<SimpleCard>(&bytes, &config), Ok(cards));
+        let empty = pack::<SimpleCard>(&[], &config).unwrap();
+        assert_eq!(empty, vec![0; 4]);
+        assert_eq!(unpack::<SimpleCard>(&empty, &config), Ok(vec![]));
+    }
+
+    #[test]
+    fn widest_cards_round_trip() {
+        // 60 bits of type and 4 of flags
+        let config = TestConfig::new(usize::MAX, 60);
+        let t = (1 << 60) - 1;
+        let cards = vec![
+            SimpleCard::Open(t, Orientation::Left),
+            SimpleCard::Masked(None, Orientation::Right),
+            SimpleCard::Masked(Some(t), Orientation::UpsideDown),
+        ];
+        let bytes = pack(&cards, &config).unwrap();
+        assert_eq!(bytes.len(), 4 + 3 * 8);
+        assert_eq!(unpack::<SimpleCard>(&bytes, &config), Ok(cards));
+    }
+
+    #[test]
+    fn rejects_types_wider_than_the_config() {
+        // Claims 100 types but only has room for 4
+        let config = TestConfig::new(100, 2);
+        let card = SimpleCard::face_up(10);
+        assert_eq!(card.to_bits(&config), Err(EncodingError::Unpackable));
+        assert_eq!(pack(&[card], &config), Err(EncodingError::Unpackable));
+        let config = TestConfig::new(52, 6);
+        assert_eq!(
+            SimpleCard::face_up(52).to_bits(&config),
+            Err(EncodingError::OutOfBounds { t: 52, T: 52 })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bits() {
+        let config = TestConfig::new(52, 6);
+        // Masked without a known type, but with type bits set
+        assert!(SimpleCard::from_bits(1 << 4 | 0b01, &config).is_err());
+        // The unused flag combination
+        assert!(SimpleCard::from_bits(0b10, &config).is_err());
+        // Above the width
+        assert!(SimpleCard::from_bits(1 << 10, &config).is_err());
+        // Type out of range
+        assert!(SimpleCard::from_bits(60 << 4, &config).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_and_padded_bytes() {
+        let config = TestConfig::new(52, 6);
+        let bytes = pack(&all_cards(&config), &config).unwrap();
+        for len in 0..bytes.len() {
+            assert!(matches!(
+                unpack::<SimpleCard>(&bytes[..len], &config),
+                Err(EncodingError::Length { .. })
+            ));
+        }
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(matches!(
+            unpack::<SimpleCard>(&padded, &config),
+            Err(EncodingError::Length { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_huge_counts() {
+        let config = TestConfig::new(52, 6);
+        let mut bytes = u32::MAX.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(matches!(
+            unpack::<SimpleCard>(&bytes, &config),
+            Err(EncodingError::Length { .. })
+        ));
+        assert!(packed_len(usize::MAX, 64).is_err());
+        assert!(packed_len(usize::MAX / 8 + 1, 8).is_err());
+    }
+
+    #[test]
+    fn rejects_unusable_widths() {
+        let config = TestConfig::new(52, 61);
+        assert_eq!(
+            pack(&[SimpleCard::face_up(0)], &config),
+            Err(EncodingError::TooWide(65))
+        );
+        assert_eq!(
+            unpack::<SimpleCard>(&[0; 4], &config),
+            Err(EncodingError::TooWide(65))
+        );
+        let config = TestConfig::new(1, 0);
+        assert_eq!(pack(&[Blank], &config), Err(EncodingError::ZeroWidth));
+        assert!(matches!(
+            unpack::<Blank>(&u32::MAX.to_le_bytes(), &config),
+            Err(EncodingError::ZeroWidth)
+        ));
+    }
+}
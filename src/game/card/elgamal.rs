@@ -0,0 +1,251 @@
+//! ElGamal encryption of cards over the Ristretto group, for games with hidden information.
+//!
+//! Every card type `t` maps to a fixed group element. Masking encrypts it under a
+//! [`PublicKey`] and opening decrypts it with the matching [`SecretKey`], so unlike a
+//! [`SimpleCard`](super::SimpleCard) a masked card doesn't reveal anything without the key.
+use crate::game::card::bits::{check_type, EncodingError};
+use crate::game::card::{CardConfig, CardEncoding};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter};
+use std::sync::OnceLock;
+
+/// Opening looks the type of a card up in a table, so only that many types are supported.
+pub const MAX_TYPES: usize = 1 << 12;
+
+/// A player's key, it opens every card masked under its [`PublicKey`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SecretKey(pub(crate) Scalar);
+impl SecretKey {
+    pub fn random() -> Self {
+        Self(Scalar::random(&mut rand::thread_rng()))
+    }
+    pub fn public(&self) -> PublicKey {
+        PublicKey(RistrettoPoint::mul_base(&self.0))
+    }
+}
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretKey(..)")
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKey(pub(crate) RistrettoPoint);
+
+/// An encrypted card type `(r·G, M_t + r·Y)` for a random `r` and the public key `Y`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ciphertext {
+    pub(crate) c1: RistrettoPoint,
+    pub(crate) c2: RistrettoPoint,
+}
+impl Ciphertext {
+    /// Panics if `t` isn't below [`MAX_TYPES`].
+    pub fn encrypt(t: usize, key: &PublicKey) -> Self {
+        assert!(t < MAX_TYPES, "card type {} exceeds {}", t, MAX_TYPES);
+        let r = Scalar::random(&mut rand::thread_rng());
+        Self {
+            c1: RistrettoPoint::mul_base(&r),
+            c2: type_point(t) + r * key.0,
+        }
+    }
+    /// Encrypts the same type again with fresh randomness, so both can't be linked anymore.
+    /// `key` has to be the one the card got encrypted with.
+    pub fn rerandomize(&self, key: &PublicKey) -> Self {
+        self.rerandomize_with(&Scalar::random(&mut rand::thread_rng()), key)
+    }
+    pub(crate) fn rerandomize_with(&self, r: &Scalar, key: &PublicKey) -> Self {
+        Self {
+            c1: self.c1 + RistrettoPoint::mul_base(r),
+            c2: self.c2 + r * key.0,
+        }
+    }
+    /// The card type, `None` if `key` is the wrong one.
+    pub fn decrypt(&self, key: &SecretKey) -> Option<usize> {
+        lookup_type(&(self.c2 - key.0 * self.c1))
+    }
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(self.c1.compress().as_bytes());
+        bytes[32..].copy_from_slice(self.c2.compress().as_bytes());
+        bytes
+    }
+    /// `None` if `bytes` don't hold two valid points.
+    pub fn from_bytes(bytes: &[u8; 64]) -> Option<Self> {
+        let point = |bytes: &[u8]| CompressedRistretto::from_slice(bytes).ok()?.decompress();
+        Some(Self {
+            c1: point(&bytes[..32])?,
+            c2: point(&bytes[32..])?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElGamalCard {
+    /// You are supposed to ensure your card isn't outside its type bounds!
+    /// e.g. assert!(t < T)
+    Open(usize),
+    /// Boxed, two uncompressed points are large compared to a type
+    Masked(Box<Ciphertext>),
+}
+/// Masking an already masked card re-randomizes it, so `key` has to be the same one.
+impl CardEncoding for ElGamalCard {
+    type MaskKey = PublicKey;
+    type OpenKey = SecretKey;
+
+    fn t(&self) -> Option<usize> {
+        match self {
+            ElGamalCard::Open(t) => Some(*t),
+            ElGamalCard::Masked(_) => None,
+        }
+    }
+    fn is_masked(&self) -> bool {
+        matches!(self, ElGamalCard::Masked(_))
+    }
+    fn mask(self, key: &PublicKey) -> Self {
+        match self {
+            ElGamalCard::Open(t) => ElGamalCard::Masked(Box::new(Ciphertext::encrypt(t, key))),
+            ElGamalCard::Masked(ciphertext) => {
+                ElGamalCard::Masked(Box::new(ciphertext.rerandomize(key)))
+            }
+        }
+    }
+    fn open(self, key: &SecretKey) -> Self {
+        match &self {
+            ElGamalCard::Masked(ciphertext) => ciphertext.decrypt(key).map_or(self, Self::Open),
+            ElGamalCard::Open(_) => self,
+        }
+    }
+    /// Only open cards fit, masked ones need [`Ciphertext::to_bytes`].
+    fn bit_width(w: u32) -> u32 {
+        w
+    }
+    fn to_bits(&self, config: &impl CardConfig) -> Result<u64, EncodingError> {
+        match self {
+            ElGamalCard::Open(t) => Ok(check_type(*t, config)? as u64),
+            ElGamalCard::Masked(_) => Err(EncodingError::Unpackable),
+        }
+    }
+    fn from_bits(bits: u64, config: &impl CardConfig) -> Result<Self, EncodingError> {
+        let t = usize::try_from(bits).map_err(|_| EncodingError::InvalidBits(bits))?;
+        Ok(ElGamalCard::Open(check_type(t, config)?))
+    }
+}
+
+/// Fiat-Shamir challenge of a proof, binding it to `label` and all of `points`.
+pub(crate) fn challenge(label: &[u8], points: &[&RistrettoPoint]) -> Scalar {
+    let mut input = label.to_vec();
+    for point in points {
+        input.extend_from_slice(point.compress().as_bytes());
+    }
+    Scalar::hash_from_bytes::<Sha512>(&input)
+}
+
+/// The group element `M_t` card type `t` gets encrypted as.
+pub fn type_point(t: usize) -> RistrettoPoint {
+    let mut input = b"mcg_visual card type".to_vec();
+    input.extend_from_slice(&(t as u64).to_le_bytes());
+    RistrettoPoint::hash_from_bytes::<Sha512>(&input)
+}
+
+pub(crate) fn lookup_type(point: &RistrettoPoint) -> Option<usize> {
+    static TYPES: OnceLock<HashMap<CompressedRistretto, usize>> = OnceLock::new();
+    TYPES
+        .get_or_init(|| {
+            (0..MAX_TYPES)
+                .map(|t| (type_point(t).compress(), t))
+                .collect()
+        })
+        .get(&point.compress())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::DirectoryCardType;
+
+    fn deck(types: usize) -> DirectoryCardType {
+        let names = (0..types).map(|t| format!("{t:02}.png")).collect();
+        DirectoryCardType::new("test".to_string(), names, egui::vec2(1.0, 1.0))
+    }
+
+    #[test]
+    fn mask_then_open_every_type() {
+        let key = SecretKey::random();
+        let config = deck(52);
+        for t in 0..config.T() {
+            let masked = ElGamalCard::Open(t).mask(&key.public());
+            assert!(masked.is_masked());
+            assert_eq!(masked.t(), None);
+            assert_eq!(masked.open(&key), ElGamalCard::Open(t));
+        }
+        let last = MAX_TYPES - 1;
+        let masked = ElGamalCard::Open(last).mask(&key.public());
+        assert_eq!(masked.open(&key), ElGamalCard::Open(last));
+    }
+
+    #[test]
+    fn wrong_key_keeps_card_masked() {
+        let key = SecretKey::random();
+        let masked = ElGamalCard::Open(7).mask(&key.public());
+        let opened = masked.clone().open(&SecretKey::random());
+        assert_eq!(opened, masked);
+    }
+
+    #[test]
+    fn rerandomized_cards_differ_but_open_the_same() {
+        let key = SecretKey::random();
+        let ciphertext = Ciphertext::encrypt(3, &key.public());
+        let again = ciphertext.rerandomize(&key.public());
+        assert_ne!(ciphertext, again);
+        assert_eq!(again.decrypt(&key), Some(3));
+        let remasked = ElGamalCard::Masked(Box::new(ciphertext)).mask(&key.public());
+        assert_eq!(remasked.open(&key), ElGamalCard::Open(3));
+    }
+
+    #[test]
+    fn ciphertext_bytes_round_trip() {
+        let key = SecretKey::random();
+        let ciphertext = Ciphertext::encrypt(11, &key.public());
+        let bytes = ciphertext.to_bytes();
+        assert_eq!(Ciphertext::from_bytes(&bytes), Some(ciphertext));
+        assert_eq!(Ciphertext::from_bytes(&[0xff; 64]), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn encrypt_rejects_types_beyond_the_table() {
+        Ciphertext::encrypt(MAX_TYPES, &SecretKey::random().public());
+    }
+
+    #[test]
+    fn lookup_covers_exactly_the_table() {
+        assert_eq!(lookup_type(&type_point(0)), Some(0));
+        assert_eq!(lookup_type(&type_point(MAX_TYPES - 1)), Some(MAX_TYPES - 1));
+        assert_eq!(lookup_type(&type_point(MAX_TYPES)), None);
+    }
+
+    #[test]
+    fn only_open_cards_have_bits() {
+        let config = deck(52);
+        for t in 0..config.T() {
+            let bits = ElGamalCard::Open(t).to_bits(&config).unwrap();
+            assert_eq!(
+                ElGamalCard::from_bits(bits, &config),
+                Ok(ElGamalCard::Open(t))
+            );
+        }
+        assert_eq!(
+            ElGamalCard::Open(52).to_bits(&config),
+            Err(EncodingError::OutOfBounds { t: 52, T: 52 })
+        );
+        assert!(ElGamalCard::from_bits(52, &config).is_err());
+        let masked = ElGamalCard::Open(0).mask(&SecretKey::random().public());
+        assert_eq!(masked.to_bits(&config), Err(EncodingError::Unpackable));
+    }
+}
@@ -0,0 +1,226 @@
+//! Card sets compiled into the binary, so they work without any files next to it.
+//!
+//! ```ignore
+//! static MY_DECK: EmbeddedCardSet =
+//!     mcg_visual::embed_card_set!("my_deck", "../media/my_deck", ["back.png", "ace.png"]);
+//! ```
+//!
+//! The directory is relative to the file invoking [`embed_card_set!`](crate::embed_card_set),
+//! like for [`include_bytes!`]. A `cards.toml` or `cards.json` among the files is used as
+//! [`Manifest`].
+use crate::game::card::manifest::Manifest;
+use crate::game::card::{DirectoryCardType, ImageOrigin};
+use egui::load::Bytes;
+use egui::Vec2;
+
+/// Files of a card set included with [`include_bytes!`], egui serves them under
+/// `bytes://{name}/{file}` once [`EmbeddedCardSet::install`]ed.
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedCardSet {
+    name: &'static str,
+    files: &'static [(&'static str, &'static [u8])],
+}
+/// Builder
+impl EmbeddedCardSet {
+    /// Prefer [`embed_card_set!`](crate::embed_card_set), it reads the files.
+    pub const fn new(name: &'static str, files: &'static [(&'static str, &'static [u8])]) -> Self {
+        Self { name, files }
+    }
+}
+/// Getter
+impl EmbeddedCardSet {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    pub fn bytes(&self, file: &str) -> Option<&'static [u8]> {
+        self.files
+            .iter()
+            .find(|(name, _)| *name == file)
+            .map(|(_, bytes)| *bytes)
+    }
+    pub fn uri(&self, file: &str) -> String {
+        format!("bytes://{}/{file}", self.name)
+    }
+}
+/// Utility
+impl EmbeddedCardSet {
+    /// Makes the files known to egui, needed before any of its cards get shown.
+    pub fn install(&self, ctx: &egui::Context) {
+        for (file, bytes) in self.files {
+            ctx.include_bytes(self.uri(file), Bytes::Static(bytes));
+        }
+    }
+    /// A [`DirectoryCardType`] of all images in the set, sized like the first one.
+    ///
+    /// A broken manifest gets logged and ignored, the images are used by name then.
+    pub fn card_type(&self) -> DirectoryCardType {
+        let mut img_names: Vec<String> = self
+            .files
+            .iter()
+            .map(|(file, _)| *file)
+            .filter(|file| {
+                image::ImageFormat::from_path(file).is_ok_and(|format| format.reading_enabled())
+            })
+            .map(str::to_string)
+            .collect();
+        img_names.sort();
+        let natural_size = img_names
+            .first()
+            .and_then(|file| self.bytes(file))
+            .and_then(|bytes| {
+                image::ImageReader::new(std::io::Cursor::new(bytes))
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            })
+            .map_or(Vec2::ZERO, |(width, height)| {
+                egui::vec2(width as f32, height as f32)
+            });
+        let manifest = Manifest::FILE_NAMES.iter().find_map(|file_name| {
+            let bytes = self.bytes(file_name)?;
+            let parsed = std::str::from_utf8(bytes)
+                .map_err(|err| err.to_string())
+                .and_then(|text| Manifest::parse(file_name, text).map_err(|err| err.to_string()));
+            parsed
+                .map_err(|err| {
+                    crate::sprintln!("Ignoring manifest {} of {}: {}", file_name, self.name, err)
+                })
+                .ok()
+        });
+        DirectoryCardType::assemble(
+            self.name.to_string(),
+            img_names,
+            natural_size,
+            manifest,
+            ImageOrigin::Embedded,
+        )
+    }
+}
+
+/// Builds an [`EmbeddedCardSet`] named `$name` from `$file`s inside `$dir`, see the
+/// [module docs](crate::game::card::embedded).
+#[macro_export]
+macro_rules! embed_card_set {
+    ($name:expr, $dir:literal, [$($file:literal),* $(,)?]) => {
+        $crate::game::card::embedded::EmbeddedCardSet::new(
+            $name,
+            &[$(($file, include_bytes!(concat!($dir, "/", $file)) as &[u8])),*],
+        )
+    };
+}
+
+/// The 52 French-suited cards, ordered like [`ConventionalCard::t`](crate::example::ConventionalCard::t).
+pub fn default_deck() -> &'static EmbeddedCardSet {
+    static DECK: EmbeddedCardSet = embed_card_set!(
+        "mcg_visual/default_deck",
+        "../../../media/default_deck",
+        [
+            "cards.toml",
+            "back.png",
+            "1_heart.png",
+            "2_heart.png",
+            "3_heart.png",
+            "4_heart.png",
+            "5_heart.png",
+            "6_heart.png",
+            "7_heart.png",
+            "8_heart.png",
+            "9_heart.png",
+            "10_heart.png",
+            "11_heart.png",
+            "12_heart.png",
+            "13_heart.png",
+            "1_diamond.png",
+            "2_diamond.png",
+            "3_diamond.png",
+            "4_diamond.png",
+            "5_diamond.png",
+            "6_diamond.png",
+            "7_diamond.png",
+            "8_diamond.png",
+            "9_diamond.png",
+            "10_diamond.png",
+            "11_diamond.png",
+            "12_diamond.png",
+            "13_diamond.png",
+            "1_club.png",
+            "2_club.png",
+            "3_club.png",
+            "4_club.png",
+            "5_club.png",
+            "6_club.png",
+            "7_club.png",
+            "8_club.png",
+            "9_club.png",
+            "10_club.png",
+            "11_club.png",
+            "12_club.png",
+            "13_club.png",
+            "1_spade.png",
+            "2_spade.png",
+            "3_spade.png",
+            "4_spade.png",
+            "5_spade.png",
+            "6_spade.png",
+            "7_spade.png",
+            "8_spade.png",
+            "9_spade.png",
+            "10_spade.png",
+            "11_spade.png",
+            "12_spade.png",
+            "13_spade.png",
+        ]
+    );
+    &DECK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::painted::PaintedCardType;
+    use crate::game::card::CardConfig;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn default_deck_matches_painted_cards() {
+        let deck = default_deck().card_type();
+        let painted = PaintedCardType::new();
+        assert!(deck.manifest().is_some(), "cards.toml must parse");
+        assert!(deck.manifest_warnings().is_empty());
+        assert_eq!(deck.T(), 52);
+        assert_eq!(deck.back().uri, default_deck().uri("back.png"));
+        for t in 0..deck.T() {
+            assert_eq!(deck.face(t).name, painted.face(t).name);
+            assert_eq!(deck.face(t).uri, painted.face(t).uri);
+            assert_eq!(
+                deck.face(t).property("rank"),
+                painted.face(t).property("rank")
+            );
+        }
+    }
+
+    #[test]
+    fn default_deck_embeds_the_whole_directory() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/media/default_deck");
+        let on_disk: BTreeSet<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        let embedded: BTreeSet<String> = default_deck()
+            .files
+            .iter()
+            .map(|(file, _)| file.to_string())
+            .collect();
+        assert_eq!(embedded, on_disk);
+        let manifest = default_deck().card_type().manifest().cloned().unwrap();
+        for card in &manifest.cards {
+            assert!(
+                embedded.contains(&card.image),
+                "{} isn't embedded",
+                card.image
+            );
+        }
+        assert_eq!(manifest.cards.len() + 2, embedded.len());
+    }
+}
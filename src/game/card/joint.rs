@@ -0,0 +1,446 @@
+//! Cards masked under the combined key of all players.
+//!
+//! Nobody can open such a card alone. Every player contributes an [`UnmaskShare`] per card,
+//! which comes with a proof that it's honest, and the card opens once all shares are
+//! combined. For a private draw the recipient collects the shares of everybody else and
+//! adds their own last with [`JointKey::open_privately`], so only they learn the type.
+//!
+//! Players join with a [`KeyAnnouncement`] proving they know the secret behind their key.
+//! Otherwise the last one could announce `X - ΣY_i` for an `X` of their own and open
+//! every card alone.
+use crate::game::card::elgamal::{
+    challenge, lookup_type, Ciphertext, ElGamalCard, PublicKey, SecretKey,
+};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevealError {
+    /// There is no share of this player
+    MissingShare(usize),
+    /// The share of this player doesn't belong to the card or its proof is wrong
+    InvalidShare(usize),
+    /// The share names a player that isn't part of the key
+    UnknownPlayer(usize),
+    /// The card is open already
+    NotMasked,
+    /// All shares are valid, but the card wasn't masked under this key
+    NoCardType,
+}
+impl Display for RevealError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevealError::MissingShare(player) => write!(f, "share of player {player} is missing"),
+            RevealError::InvalidShare(player) => write!(f, "share of player {player} is invalid"),
+            RevealError::UnknownPlayer(player) => write!(f, "there is no player {player}"),
+            RevealError::NotMasked => write!(f, "card isn't masked"),
+            RevealError::NoCardType => write!(f, "card wasn't masked under this key"),
+        }
+    }
+}
+impl std::error::Error for RevealError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /// A joint key of nobody would leave cards readable for everybody
+    NoPlayers,
+    /// The announcement at this position isn't the one of that player or its proof is wrong
+    InvalidProof(usize),
+}
+impl Display for KeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::NoPlayers => write!(f, "a joint key needs at least one player"),
+            KeyError::InvalidProof(player) => {
+                write!(f, "key of player {player} comes without a valid proof")
+            }
+        }
+    }
+}
+impl std::error::Error for KeyError {}
+
+/// A player's public key together with a Schnorr proof that they know its secret key,
+/// bound to their position in the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyAnnouncement {
+    pub player: usize,
+    key: PublicKey,
+    proof: KeyProof,
+}
+impl KeyAnnouncement {
+    pub fn new(player: usize, secret: &SecretKey) -> Self {
+        let key = secret.public();
+        Self {
+            player,
+            proof: KeyProof::new(player, secret, &key),
+            key,
+        }
+    }
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+    pub fn verify(&self) -> bool {
+        self.proof.verify(self.player, &self.key)
+    }
+}
+
+/// Schnorr proof of knowledge of `log_G(Y)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct KeyProof {
+    challenge: Scalar,
+    response: Scalar,
+}
+impl KeyProof {
+    fn label(player: usize) -> Vec<u8> {
+        let mut label = b"mcg_visual key proof".to_vec();
+        label.extend_from_slice(&(player as u64).to_le_bytes());
+        label
+    }
+    fn new(player: usize, secret: &SecretKey, public: &PublicKey) -> Self {
+        let k = Scalar::random(&mut rand::thread_rng());
+        let a = RistrettoPoint::mul_base(&k);
+        let challenge = challenge(&Self::label(player), &[&public.0, &a]);
+        Self {
+            challenge,
+            response: k + challenge * secret.0,
+        }
+    }
+    fn verify(&self, player: usize, public: &PublicKey) -> bool {
+        let a = RistrettoPoint::mul_base(&self.response) - self.challenge * public.0;
+        challenge(&Self::label(player), &[&public.0, &a]) == self.challenge
+    }
+}
+
+/// A player's part of unmasking one card, `x_i·c1` for their secret key `x_i`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnmaskShare {
+    pub player: usize,
+    share: RistrettoPoint,
+    proof: DleqProof,
+}
+
+/// Chaum-Pedersen proof that `log_G(Y) = log_c1(D)`, i.e. the share got computed with the
+/// secret key belonging to the player's public key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DleqProof {
+    challenge: Scalar,
+    response: Scalar,
+}
+impl DleqProof {
+    const LABEL: &'static [u8] = b"mcg_visual unmask share";
+
+    fn new(secret: &SecretKey, c1: &RistrettoPoint, share: &RistrettoPoint) -> Self {
+        let public = secret.public();
+        let k = Scalar::random(&mut rand::thread_rng());
+        let a = RistrettoPoint::mul_base(&k);
+        let b = k * c1;
+        let challenge = challenge(Self::LABEL, &[&public.0, c1, share, &a, &b]);
+        Self {
+            challenge,
+            response: k + challenge * secret.0,
+        }
+    }
+    fn verify(&self, public: &PublicKey, c1: &RistrettoPoint, share: &RistrettoPoint) -> bool {
+        let a = RistrettoPoint::mul_base(&self.response) - self.challenge * public.0;
+        let b = self.response * c1 - self.challenge * share;
+        challenge(Self::LABEL, &[&public.0, c1, share, &a, &b]) == self.challenge
+    }
+}
+
+/// The public keys of all players of a game, in player order.
+///
+/// Deserializing verifies the announcements again, like [`JointKey::new`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<KeyAnnouncement>", into = "Vec<KeyAnnouncement>")]
+pub struct JointKey {
+    players: Vec<KeyAnnouncement>,
+    combined: PublicKey,
+}
+impl JointKey {
+    /// Combines the keys of all players, `announcements[i]` has to be the one of player `i`.
+    pub fn new(announcements: Vec<KeyAnnouncement>) -> Result<Self, KeyError> {
+        if announcements.is_empty() {
+            return Err(KeyError::NoPlayers);
+        }
+        for (player, announcement) in announcements.iter().enumerate() {
+            if announcement.player != player || !announcement.verify() {
+                return Err(KeyError::InvalidProof(player));
+            }
+        }
+        let combined = PublicKey(announcements.iter().map(|a| a.key.0).sum());
+        Ok(Self {
+            players: announcements,
+            combined,
+        })
+    }
+    /// The key to [`CardEncoding::mask`](crate::game::card::CardEncoding::mask) cards
+    /// with, so opening them needs every player.
+    pub fn public(&self) -> &PublicKey {
+        &self.combined
+    }
+    pub fn players(&self) -> &[KeyAnnouncement] {
+        &self.players
+    }
+    fn player(&self, player: usize) -> Result<&PublicKey, RevealError> {
+        self.players
+            .get(player)
+            .map(KeyAnnouncement::key)
+            .ok_or(RevealError::UnknownPlayer(player))
+    }
+    /// Computes the share of `player` for unmasking `card`.
+    pub fn share(
+        &self,
+        player: usize,
+        secret: &SecretKey,
+        card: &ElGamalCard,
+    ) -> Result<UnmaskShare, RevealError> {
+        let ciphertext = masked(card)?;
+        if self.player(player)? != &secret.public() {
+            return Err(RevealError::UnknownPlayer(player));
+        }
+        let share = secret.0 * ciphertext.c1;
+        Ok(UnmaskShare {
+            player,
+            proof: DleqProof::new(secret, &ciphertext.c1, &share),
+            share,
+        })
+    }
+    /// Whether `share` is the honest share of its player for `card`.
+    pub fn verify_share(&self, card: &ElGamalCard, share: &UnmaskShare) -> Result<(), RevealError> {
+        let ciphertext = masked(card)?;
+        let public = self.player(share.player)?;
+        if share.proof.verify(public, &ciphertext.c1, &share.share) {
+            Ok(())
+        } else {
+            Err(RevealError::InvalidShare(share.player))
+        }
+    }
+    /// Opens `card` with the shares of all players, verifying each of them.
+    pub fn open(
+        &self,
+        card: &ElGamalCard,
+        shares: &[UnmaskShare],
+    ) -> Result<ElGamalCard, RevealError> {
+        self.combine(card, shares, None)
+    }
+    /// Opens `card` for `player` only: `shares` hold the shares of all other players and
+    /// the player's own one gets added locally, so it never has to leave their client.
+    pub fn open_privately(
+        &self,
+        card: &ElGamalCard,
+        player: usize,
+        secret: &SecretKey,
+        shares: &[UnmaskShare],
+    ) -> Result<ElGamalCard, RevealError> {
+        let own = self.share(player, secret, card)?;
+        self.combine(card, shares, Some(own))
+    }
+    fn combine(
+        &self,
+        card: &ElGamalCard,
+        shares: &[UnmaskShare],
+        own: Option<UnmaskShare>,
+    ) -> Result<ElGamalCard, RevealError> {
+        let ciphertext = masked(card)?;
+        let mut combined = RistrettoPoint::default();
+        for player in 0..self.players.len() {
+            let share = match &own {
+                Some(own) if own.player == player => own,
+                _ => shares
+                    .iter()
+                    .find(|share| share.player == player)
+                    .ok_or(RevealError::MissingShare(player))?,
+            };
+            self.verify_share(card, share)?;
+            combined += share.share;
+        }
+        lookup_type(&(ciphertext.c2 - combined))
+            .map(ElGamalCard::Open)
+            .ok_or(RevealError::NoCardType)
+    }
+}
+
+impl TryFrom<Vec<KeyAnnouncement>> for JointKey {
+    type Error = KeyError;
+
+    fn try_from(announcements: Vec<KeyAnnouncement>) -> Result<Self, KeyError> {
+        Self::new(announcements)
+    }
+}
+impl From<JointKey> for Vec<KeyAnnouncement> {
+    fn from(key: JointKey) -> Self {
+        key.players
+    }
+}
+
+fn masked(card: &ElGamalCard) -> Result<&Ciphertext, RevealError> {
+    match card {
+        ElGamalCard::Masked(ciphertext) => Ok(ciphertext),
+        ElGamalCard::Open(_) => Err(RevealError::NotMasked),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::CardEncoding;
+
+    fn players(count: usize) -> (Vec<SecretKey>, JointKey) {
+        let secrets: Vec<SecretKey> = (0..count).map(|_| SecretKey::random()).collect();
+        let announcements = secrets
+            .iter()
+            .enumerate()
+            .map(|(player, secret)| KeyAnnouncement::new(player, secret))
+            .collect();
+        (secrets, JointKey::new(announcements).unwrap())
+    }
+    fn shares(key: &JointKey, secrets: &[SecretKey], card: &ElGamalCard) -> Vec<UnmaskShare> {
+        secrets
+            .iter()
+            .enumerate()
+            .map(|(player, secret)| key.share(player, secret, card).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn honest_shares_verify_and_open() {
+        let (secrets, key) = players(3);
+        for t in [0, 1, 51] {
+            let card = ElGamalCard::Open(t).mask(key.public());
+            let shares = shares(&key, &secrets, &card);
+            for share in &shares {
+                assert_eq!(key.verify_share(&card, share), Ok(()));
+            }
+            assert_eq!(key.open(&card, &shares), Ok(ElGamalCard::Open(t)));
+        }
+    }
+
+    #[test]
+    fn no_single_player_opens_a_card() {
+        let (secrets, key) = players(3);
+        let card = ElGamalCard::Open(5).mask(key.public());
+        for secret in &secrets {
+            assert!(card.clone().open(secret).is_masked());
+        }
+    }
+
+    #[test]
+    fn bad_shares_are_rejected() {
+        let (secrets, key) = players(3);
+        let card = ElGamalCard::Open(5).mask(key.public());
+        let other = ElGamalCard::Open(5).mask(key.public());
+        let mut shares = shares(&key, &secrets, &card);
+        // A share for another card
+        let foreign = key.share(1, &secrets[1], &other).unwrap();
+        assert_eq!(
+            key.verify_share(&card, &foreign),
+            Err(RevealError::InvalidShare(1))
+        );
+        // A share whose point got replaced, keeping the proof
+        let mut forged = shares[2].clone();
+        forged.share = foreign.share;
+        assert_eq!(
+            key.verify_share(&card, &forged),
+            Err(RevealError::InvalidShare(2))
+        );
+        shares[2] = forged;
+        assert_eq!(key.open(&card, &shares), Err(RevealError::InvalidShare(2)));
+        shares.pop();
+        assert_eq!(key.open(&card, &shares), Err(RevealError::MissingShare(2)));
+        let mut stranger = foreign;
+        stranger.player = 3;
+        assert_eq!(
+            key.verify_share(&card, &stranger),
+            Err(RevealError::UnknownPlayer(3))
+        );
+        assert_eq!(
+            key.share(0, &secrets[1], &card),
+            Err(RevealError::UnknownPlayer(0))
+        );
+        assert_eq!(
+            key.open(&ElGamalCard::Open(5), &[]),
+            Err(RevealError::NotMasked)
+        );
+    }
+
+    #[test]
+    fn private_reveal_needs_everybody_else() {
+        let (secrets, key) = players(3);
+        let card = ElGamalCard::Open(9).mask(key.public());
+        let others: Vec<UnmaskShare> = [0, 2]
+            .iter()
+            .map(|&player| key.share(player, &secrets[player], &card).unwrap())
+            .collect();
+        assert_eq!(
+            key.open_privately(&card, 1, &secrets[1], &others),
+            Ok(ElGamalCard::Open(9))
+        );
+        // Without the recipient's secret the other shares don't suffice
+        assert_eq!(key.open(&card, &others), Err(RevealError::MissingShare(1)));
+        assert_eq!(
+            key.open_privately(&card, 1, &secrets[1], &others[..1]),
+            Err(RevealError::MissingShare(2))
+        );
+        let mut bad = others.clone();
+        bad[0] = key.share(1, &secrets[1], &card).unwrap();
+        bad[0].player = 0;
+        assert_eq!(
+            key.open_privately(&card, 1, &secrets[1], &bad),
+            Err(RevealError::InvalidShare(0))
+        );
+    }
+
+    #[test]
+    fn rogue_keys_are_rejected() {
+        let secrets: Vec<SecretKey> = (0..2).map(|_| SecretKey::random()).collect();
+        let mut announcements: Vec<KeyAnnouncement> = secrets
+            .iter()
+            .enumerate()
+            .map(|(player, secret)| KeyAnnouncement::new(player, secret))
+            .collect();
+        // The last player cancels the others' keys out, but can't prove to know the secret
+        let attacker = SecretKey::random();
+        let honest: RistrettoPoint = announcements.iter().map(|a| a.key.0).sum();
+        let rogue = PublicKey(attacker.public().0 - honest);
+        announcements.push(KeyAnnouncement {
+            player: 2,
+            key: rogue,
+            proof: KeyProof::new(2, &attacker, &rogue),
+        });
+        assert_eq!(
+            JointKey::new(announcements.clone()),
+            Err(KeyError::InvalidProof(2))
+        );
+        // Neither can a valid announcement be replayed at another position
+        announcements[2] = announcements[0].clone();
+        assert_eq!(
+            JointKey::new(announcements.clone()),
+            Err(KeyError::InvalidProof(2))
+        );
+        announcements[2] = KeyAnnouncement {
+            player: 2,
+            ..announcements[0].clone()
+        };
+        assert_eq!(
+            JointKey::new(announcements.clone()),
+            Err(KeyError::InvalidProof(2))
+        );
+        announcements.truncate(2);
+        assert!(JointKey::new(announcements).is_ok());
+        assert_eq!(JointKey::new(vec![]), Err(KeyError::NoPlayers));
+    }
+
+    #[test]
+    fn deserializing_verifies_again() {
+        let (_, key) = players(2);
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(serde_json::from_str::<JointKey>(&json).unwrap(), key);
+        let mut announcements = key.players().to_vec();
+        announcements.swap(0, 1);
+        let json = serde_json::to_string(&announcements).unwrap();
+        assert!(serde_json::from_str::<JointKey>(&json).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Optional description of a card set, stored next to its images as `cards.toml` or
+//! `cards.json`.
+//!
+//! ```toml
+//! back = "back.png"
+//!
+//! [[cards]]
+//! image = "ace_spades.png"
+//! name = "Ace of Spades"
+//! order = 0
+//! tags = ["spades", "ace"]
+//! count = 2
+//! properties = { value = 11 }
+//! ```
+//!
+//! Every field but `image` is optional. Images without an entry still become card types,
+//! ordered after all listed ones.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    /// Image used as back instead of one named [`DirectoryCardType::BACK_NAME`](super::DirectoryCardType::BACK_NAME)
+    pub back: Option<String>,
+    pub cards: Vec<CardEntry>,
+}
+impl Manifest {
+    /// File names a manifest is looked up under, in order of preference.
+    pub const FILE_NAMES: [&'static str; 2] = ["cards.toml", "cards.json"];
+
+    /// Parses `text` as TOML or JSON, depending on the extension of `file_name`.
+    pub fn parse(file_name: &str, text: &str) -> Result<Self, ManifestError> {
+        if file_name.ends_with(".toml") {
+            toml::from_str(text).map_err(|err| ManifestError::Parse(err.to_string()))
+        } else if file_name.ends_with(".json") {
+            serde_json::from_str(text).map_err(|err| ManifestError::Parse(err.to_string()))
+        } else {
+            Err(ManifestError::Format(file_name.to_string()))
+        }
+    }
+    pub fn entry(&self, image: &str) -> Option<&CardEntry> {
+        self.cards.iter().find(|entry| entry.image == image)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardEntry {
+    /// File name of the image inside the card set's directory
+    pub image: String,
+    /// Defaults to the image's file stem
+    pub name: Option<String>,
+    /// Types get sorted by it, ties and missing orders by file name
+    pub order: Option<i64>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Copies of this card in a fresh deck
+    #[serde(default = "CardEntry::default_count")]
+    pub count: usize,
+    /// Free-form values, numbers and booleans get stored as text
+    #[serde(default)]
+    pub properties: BTreeMap<String, Property>,
+}
+impl CardEntry {
+    fn default_count() -> usize {
+        1
+    }
+}
+
+/// A property value as it's written in the manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+impl Display for Property {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Property::Bool(value) => write!(f, "{value}"),
+            Property::Int(value) => write!(f, "{value}"),
+            Property::Float(value) => write!(f, "{value}"),
+            Property::Text(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// Neither a `.toml` nor a `.json` file
+    Format(String),
+    Parse(String),
+}
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Format(file_name) => write!(f, "{file_name} is no TOML or JSON file"),
+            ManifestError::Parse(err) => write!(f, "invalid manifest: {err}"),
+        }
+    }
+}
+impl std::error::Error for ManifestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+back = "back.png"
+
+[[cards]]
+image = "ace.png"
+name = "Ace"
+order = 1
+tags = ["high"]
+count = 2
+properties = { value = 11, wild = false, suit = "spades", weight = 1.5 }
+
+[[cards]]
+image = "two.png"
+"#;
+
+    const JSON: &str = r#"{
+        "back": "back.png",
+        "cards": [
+            {
+                "image": "ace.png",
+                "name": "Ace",
+                "order": 1,
+                "tags": ["high"],
+                "count": 2,
+                "properties": { "value": 11, "wild": false, "suit": "spades", "weight": 1.5 }
+            },
+            { "image": "two.png" }
+        ]
+    }"#;
+
+    #[test]
+    fn both_formats_parse_the_same() {
+        let toml = Manifest::parse("cards.toml", TOML).unwrap();
+        let json = Manifest::parse("cards.json", JSON).unwrap();
+        assert_eq!(toml, json);
+        assert_eq!(toml.back.as_deref(), Some("back.png"));
+        let ace = toml.entry("ace.png").unwrap();
+        assert_eq!(ace.name.as_deref(), Some("Ace"));
+        assert_eq!(ace.order, Some(1));
+        assert!(ace.tags.contains("high"));
+        assert_eq!(ace.count, 2);
+        assert_eq!(ace.properties["value"], Property::Int(11));
+        assert_eq!(ace.properties["wild"], Property::Bool(false));
+        assert_eq!(ace.properties["suit"].to_string(), "spades");
+        assert_eq!(ace.properties["weight"], Property::Float(1.5));
+    }
+
+    #[test]
+    fn missing_fields_get_defaults() {
+        let manifest = Manifest::parse("cards.toml", TOML).unwrap();
+        let two = manifest.entry("two.png").unwrap();
+        assert_eq!((two.name.as_ref(), two.order), (None, None));
+        assert!(two.tags.is_empty() && two.properties.is_empty());
+        assert_eq!(two.count, 1);
+        assert_eq!(manifest.entry("three.png"), None);
+        assert_eq!(Manifest::parse("cards.json", "{}"), Ok(Manifest::default()));
+    }
+
+    #[test]
+    fn broken_manifests_are_errors() {
+        assert!(matches!(
+            Manifest::parse("cards.toml", "back = "),
+            Err(ManifestError::Parse(_))
+        ));
+        assert!(matches!(
+            Manifest::parse("cards.json", r#"{"cards": [{"name": "no image"}]}"#),
+            Err(ManifestError::Parse(_))
+        ));
+        assert_eq!(
+            Manifest::parse("cards.yaml", ""),
+            Err(ManifestError::Format("cards.yaml".to_string()))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! A French-suited deck painted with egui's painter, so it works without any image files.
+use crate::example::{ConventionalCard, Rank, Suit};
+use crate::game::card::embedded::default_deck;
+use crate::game::card::{CardConfig, CardDescriptor, CardEncoding};
+use egui::emath::Rot2;
+use egui::epaint::TextShape;
+use egui::{pos2, vec2, Color32, FontId, Pos2, Rect, Shape, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::ops::Index;
+
+/// The 52 cards of [`ConventionalCard`], type `t` is [`ConventionalCard::t`].
+///
+/// The descriptors point to the same cards of the embedded [`default_deck`], so
+/// [`CardConfig::img`] works without files once that's installed, which
+/// [`run_native`](crate::run_native) and [`start_game`](crate::start_game) do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaintedCardType {
+    faces: Vec<CardDescriptor>,
+    back: CardDescriptor,
+}
+impl PaintedCardType {
+    pub const T: usize = Suit::len() * Rank::len();
+
+    pub fn new() -> Self {
+        let faces = (0..Self::T)
+            .map(|t| {
+                let card = ConventionalCard::from_t(t).expect("t is below T");
+                let mut face = CardDescriptor::new(
+                    format!("{} of {}s", card.rank, card.suit),
+                    default_deck().uri(&card.img_name()),
+                );
+                face.tags.insert(card.suit.to_string().to_lowercase());
+                face.metadata
+                    .insert("rank".to_string(), (card.rank as usize + 1).to_string());
+                face
+            })
+            .collect();
+        Self {
+            faces,
+            back: CardDescriptor::new("Back", default_deck().uri("back.png")),
+        }
+    }
+    /// Suit and rank of type `t`.
+    pub fn card(&self, t: usize) -> Option<ConventionalCard> {
+        ConventionalCard::from_t(t)
+    }
+}
+impl Default for PaintedCardType {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl CardConfig for PaintedCardType {
+    fn face(&self, t: usize) -> &CardDescriptor {
+        &self.faces[t]
+    }
+    fn back(&self) -> &CardDescriptor {
+        &self.back
+    }
+    fn paint(&self, ui: &egui::Ui, card: &impl CardEncoding, rect: Rect) {
+        let canvas = Canvas {
+            painter: ui.painter().clone(),
+            rect,
+            rotation: Rot2::from_angle(card.orientation().angle()),
+            angle: card.orientation().angle(),
+        };
+        canvas.blank();
+        match card
+            .t()
+            .filter(|_| card.is_open())
+            .and_then(ConventionalCard::from_t)
+        {
+            Some(card) => canvas.face(card),
+            None => canvas.back(),
+        }
+    }
+    #[allow(non_snake_case)]
+    fn T(&self) -> usize {
+        Self::T
+    }
+    fn w(&self) -> u32 {
+        Self::T.next_power_of_two().ilog2()
+    }
+    /// Poker size, any other one works as well.
+    fn natural_size(&self) -> Vec2 {
+        vec2(250.0, 350.0)
+    }
+    fn draw_at(
+        &self,
+        ui: &mut egui::Ui,
+        card: &impl CardEncoding,
+        pos: Pos2,
+    ) -> egui::InnerResponse<egui::Response> {
+        let area = egui::Area::new(ui.next_auto_id()).current_pos(pos);
+        area.show(ui.ctx(), |ui| {
+            let (rect, response) =
+                ui.allocate_exact_size(self.natural_size(), egui::Sense::hover());
+            self.paint(ui, card, rect);
+            response
+        })
+    }
+}
+impl<E: CardEncoding> Index<&E> for PaintedCardType {
+    type Output = CardDescriptor;
+
+    fn index(&self, card: &E) -> &CardDescriptor {
+        self.descriptor(card)
+    }
+}
+
+const RED: Color32 = Color32::from_rgb(200, 30, 40);
+const BLACK: Color32 = Color32::from_rgb(20, 20, 20);
+
+/// Paints in card coordinates, `(0, 0)` is the top left and `(1, 1)` the bottom right
+/// corner of the upright card, whatever way it's turned.
+struct Canvas {
+    painter: egui::Painter,
+    rect: Rect,
+    rotation: Rot2,
+    angle: f32,
+}
+impl Canvas {
+    fn pos(&self, u: f32, v: f32) -> Pos2 {
+        let offset = vec2(
+            (u - 0.5) * self.rect.width(),
+            (v - 0.5) * self.rect.height(),
+        );
+        self.rect.center() + self.rotation * offset
+    }
+    /// Quarter turns keep rects axis-aligned.
+    fn rect(&self, min: Pos2, max: Pos2) -> Rect {
+        Rect::from_two_pos(self.pos(min.x, min.y), self.pos(max.x, max.y))
+    }
+    /// Lengths relative to the card's width.
+    fn len(&self, fraction: f32) -> f32 {
+        fraction * self.rect.width()
+    }
+    fn blank(&self) {
+        let rect = self.rect(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let rounding = self.len(0.06);
+        self.painter.rect_filled(rect, rounding, Color32::WHITE);
+        let stroke = Stroke::new(self.len(0.006).max(1.0), Color32::GRAY);
+        self.painter
+            .rect_stroke(rect, rounding, stroke, egui::StrokeKind::Inside);
+    }
+    fn back(&self) {
+        // Same margin on all sides
+        let margin = 0.06 * self.rect.width() / self.rect.height();
+        let inner = self.rect(pos2(0.06, margin), pos2(0.94, 1.0 - margin));
+        self.painter
+            .rect_filled(inner, self.len(0.03), Color32::from_rgb(40, 40, 120));
+        let painter = self.painter.with_clip_rect(inner);
+        let stroke = Stroke::new(self.len(0.01).max(1.0), Color32::from_rgb(150, 30, 40));
+        // Diagonal lattice, in card coordinates so it turns with the card
+        let steps = 12;
+        for i in -steps..=steps {
+            let u = i as f32 / steps as f32 * 1.4;
+            painter.line_segment([self.pos(u, 0.0), self.pos(u + 1.4, 1.0)], stroke);
+            painter.line_segment([self.pos(u + 1.4, 0.0), self.pos(u, 1.0)], stroke);
+        }
+    }
+    fn face(&self, card: ConventionalCard) {
+        let color = match card.suit {
+            Suit::Heart | Suit::Diamond => RED,
+            Suit::Club | Suit::Spade => BLACK,
+        };
+        let index = match card.rank {
+            Rank::Ace => "A".to_string(),
+            Rank::Jack => "J".to_string(),
+            Rank::Queen => "Q".to_string(),
+            Rank::King => "K".to_string(),
+            rank => (rank as usize + 1).to_string(),
+        };
+        // Corner indices, the second one upside down
+        for (u, v, flip) in [(0.09, 0.07, false), (0.91, 0.93, true)] {
+            let sign = if flip { -1.0 } else { 1.0 };
+            self.text(u, v, &index, 0.13, color, flip);
+            self.pip(card.suit, u, v + sign * 0.09, 0.045, color);
+        }
+        match card.rank {
+            Rank::Jack | Rank::Queen | Rank::King => self.court(card.suit, &index, color),
+            Rank::Ace => self.pip(card.suit, 0.5, 0.5, 0.18, color),
+            rank => {
+                for (u, v) in pip_layout(rank) {
+                    self.pip(card.suit, u, v, 0.08, color);
+                }
+            }
+        }
+    }
+    /// A framed placeholder with the rank's letter instead of a picture.
+    fn court(&self, suit: Suit, letter: &str, color: Color32) {
+        let frame = self.rect(pos2(0.22, 0.16), pos2(0.78, 0.84));
+        self.painter
+            .rect_filled(frame, self.len(0.02), Color32::from_rgb(250, 240, 200));
+        self.painter.rect_stroke(
+            frame,
+            self.len(0.02),
+            Stroke::new(self.len(0.01).max(1.0), color),
+            egui::StrokeKind::Inside,
+        );
+        self.text(0.5, 0.42, letter, 0.3, color, false);
+        self.pip(suit, 0.5, 0.66, 0.08, color);
+    }
+    /// Centered at `(u, v)`, `size` relative to the card's width.
+    fn text(&self, u: f32, v: f32, text: &str, size: f32, color: Color32, flip: bool) {
+        let galley = self.painter.layout_no_wrap(
+            text.to_string(),
+            FontId::proportional(self.len(size)),
+            color,
+        );
+        let angle = if flip { self.angle + PI } else { self.angle };
+        // Text turns around its top left corner
+        let pos = self.pos(u, v) - Rot2::from_angle(angle) * (galley.size() / 2.0);
+        self.painter
+            .add(TextShape::new(pos, galley, color).with_angle(angle));
+    }
+    /// A suit symbol centered at `(u, v)` that's `size` wide relative to the card's width.
+    fn pip(&self, suit: Suit, u: f32, v: f32, size: f32, color: Color32) {
+        // Offsets in units of the pip's half width, converted to card coordinates
+        let aspect = self.rect.width() / self.rect.height();
+        let at = |x: f32, y: f32| self.pos(u + x * size / 2.0, v + y * size / 2.0 * aspect);
+        let radius = self.len(size) / 2.0;
+        let polygon = |points: &[(f32, f32)]| {
+            Shape::convex_polygon(
+                points.iter().map(|(x, y)| at(*x, *y)).collect(),
+                color,
+                Stroke::NONE,
+            )
+        };
+        match suit {
+            Suit::Diamond => {
+                self.painter
+                    .add(polygon(&[(0.0, -1.1), (0.8, 0.0), (0.0, 1.1), (-0.8, 0.0)]));
+            }
+            Suit::Heart => {
+                self.painter
+                    .circle_filled(at(-0.48, -0.35), radius * 0.52, color);
+                self.painter
+                    .circle_filled(at(0.48, -0.35), radius * 0.52, color);
+                self.painter
+                    .add(polygon(&[(-0.98, -0.2), (0.98, -0.2), (0.0, 1.0)]));
+            }
+            Suit::Spade => {
+                self.painter
+                    .circle_filled(at(-0.48, 0.2), radius * 0.52, color);
+                self.painter
+                    .circle_filled(at(0.48, 0.2), radius * 0.52, color);
+                self.painter
+                    .add(polygon(&[(0.0, -1.0), (0.98, 0.05), (-0.98, 0.05)]));
+                self.painter
+                    .add(polygon(&[(0.0, 0.3), (0.35, 1.0), (-0.35, 1.0)]));
+            }
+            Suit::Club => {
+                self.painter
+                    .circle_filled(at(0.0, -0.5), radius * 0.45, color);
+                self.painter
+                    .circle_filled(at(-0.5, 0.15), radius * 0.45, color);
+                self.painter
+                    .circle_filled(at(0.5, 0.15), radius * 0.45, color);
+                self.painter
+                    .add(polygon(&[(0.0, 0.0), (0.35, 1.0), (-0.35, 1.0)]));
+            }
+        }
+    }
+}
+
+/// Pip centers of the number cards in card coordinates.
+fn pip_layout(rank: Rank) -> Vec<(f32, f32)> {
+    let (left, middle, right) = (0.32, 0.5, 0.68);
+    let column = |u: f32, vs: &[f32]| vs.iter().map(move |v| (u, *v)).collect::<Vec<_>>();
+    let sides = |vs: &[f32]| [column(left, vs), column(right, vs)].concat();
+    match rank {
+        Rank::Two => column(middle, &[0.22, 0.78]),
+        Rank::Three => column(middle, &[0.22, 0.5, 0.78]),
+        Rank::Four => sides(&[0.22, 0.78]),
+        Rank::Five => [sides(&[0.22, 0.78]), column(middle, &[0.5])].concat(),
+        Rank::Six => sides(&[0.22, 0.5, 0.78]),
+        Rank::Seven => [sides(&[0.22, 0.5, 0.78]), column(middle, &[0.36])].concat(),
+        Rank::Eight => [sides(&[0.22, 0.5, 0.78]), column(middle, &[0.36, 0.64])].concat(),
+        Rank::Nine => [sides(&[0.22, 0.41, 0.59, 0.78]), column(middle, &[0.5])].concat(),
+        Rank::Ten => [
+            sides(&[0.22, 0.41, 0.59, 0.78]),
+            column(middle, &[0.31, 0.69]),
+        ]
+        .concat(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::{Orientation, SimpleCard};
+
+    #[test]
+    fn types_map_to_suit_and_rank_and_back() {
+        let deck = PaintedCardType::new();
+        assert_eq!(deck.T(), 52);
+        for t in 0..deck.T() {
+            let card = deck.card(t).unwrap();
+            assert_eq!(card.t(), t);
+            assert_eq!(card.suit as usize, t / 13);
+            assert_eq!(card.rank as usize, t % 13);
+            assert_eq!(
+                deck.face(t).name,
+                format!("{} of {}s", card.rank, card.suit)
+            );
+        }
+        assert!(deck.card(52).is_none());
+        assert_eq!(deck.face(0).name, "Ace of Hearts");
+        assert_eq!(deck.face(51).name, "King of Spades");
+        assert!(deck.face(12).has_tag("heart"));
+        assert_eq!(deck.face(12).property("rank"), Some("13"));
+    }
+
+    #[test]
+    fn images_are_embedded() {
+        let deck = PaintedCardType::new();
+        let embedded = default_deck();
+        for descriptor in (0..deck.T()).map(|t| deck.face(t)).chain([deck.back()]) {
+            let file = descriptor
+                .uri
+                .strip_prefix(&embedded.uri(""))
+                .expect("Painted cards point into the default deck");
+            assert!(embedded.bytes(file).is_some(), "{} isn't embedded", file);
+        }
+    }
+
+    #[test]
+    fn paints_every_side_in_every_orientation() {
+        let deck = PaintedCardType::new();
+        let ctx = egui::Context::default();
+        let output = ctx.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let rect = Rect::from_min_size(Pos2::ZERO, deck.natural_size());
+                for orientation in [
+                    Orientation::Upright,
+                    Orientation::Right,
+                    Orientation::UpsideDown,
+                    Orientation::Left,
+                ] {
+                    for t in 0..deck.T() {
+                        deck.paint(ui, &SimpleCard::Open(t, orientation), rect);
+                    }
+                    deck.paint(ui, &SimpleCard::Masked(None, orientation), rect);
+                }
+            });
+        });
+        assert!(!output.shapes.is_empty());
+    }
+}
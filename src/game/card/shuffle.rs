@@ -0,0 +1,319 @@
+//! Verifiable shuffles of masked cards, so a deck can be shuffled without a trusted dealer.
+//!
+//! Every player in turn re-masks and permutes the deck and publishes a [`ShuffleProof`].
+//! Once everybody's shuffle got verified, nobody knows the order unless all of them
+//! colluded.
+//!
+//! The proof is a cut-and-choose one made non-interactive: for every round the shuffler
+//! commits to a second, independent shuffle of the input. A hash of everything decides per
+//! round whether they reveal how the input turned into the commitment or how the
+//! commitment turned into the output. Either reveals nothing about the real permutation.
+//! A cheater can answer only one of both per round, but since nobody interacts with them
+//! they can redo their commitments offline until the hash suits them. Passing takes them
+//! about `2^ROUNDS` attempts.
+use crate::game::card::elgamal::{Ciphertext, ElGamalCard, PublicKey};
+use curve25519_dalek::scalar::Scalar;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShuffleError {
+    /// The card at this index is open, only masked cards can be shuffled
+    NotMasked(usize),
+    /// Input, output or proof don't have the same number of cards
+    Length,
+    /// The proof doesn't hold in this round
+    InvalidProof(usize),
+}
+impl Display for ShuffleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShuffleError::NotMasked(idx) => write!(f, "card {idx} isn't masked"),
+            ShuffleError::Length => write!(f, "number of cards doesn't match"),
+            ShuffleError::InvalidProof(round) => write!(f, "proof fails in round {round}"),
+        }
+    }
+}
+impl std::error::Error for ShuffleError {}
+
+/// Card `i` of a target is card `permutation[i]` of a source, re-masked with `randomness[i]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Permutation {
+    permutation: Vec<usize>,
+    randomness: Vec<Scalar>,
+}
+impl Permutation {
+    fn random(n: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut permutation: Vec<usize> = (0..n).collect();
+        permutation.shuffle(&mut rng);
+        Self {
+            permutation,
+            randomness: (0..n).map(|_| Scalar::random(&mut rng)).collect(),
+        }
+    }
+    fn apply(&self, source: &[Ciphertext], key: &PublicKey) -> Vec<Ciphertext> {
+        self.permutation
+            .iter()
+            .zip(&self.randomness)
+            .map(|(idx, r)| source[*idx].rerandomize_with(r, key))
+            .collect()
+    }
+    fn is_valid(&self, n: usize) -> bool {
+        let mut seen = vec![false; n];
+        self.permutation.len() == n
+            && self.randomness.len() == n
+            && self
+                .permutation
+                .iter()
+                .all(|idx| *idx < n && !std::mem::replace(&mut seen[*idx], true))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Round {
+    commitment: Vec<Ciphertext>,
+    /// Input to commitment for a 0 challenge bit, commitment to output for a 1
+    opening: Permutation,
+}
+
+/// Proof that the output of [`shuffle`] holds the same cards as its input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShuffleProof {
+    rounds: Vec<Round>,
+}
+impl ShuffleProof {
+    /// Soundness of the proof, a cheating shuffler needs about `2^ROUNDS` hash evaluations
+    /// to pass. Every round takes one bit of the 512 bit challenge.
+    pub const ROUNDS: usize = 128;
+
+    /// Checks that `output` is a re-masked permutation of `input` under `key`.
+    pub fn verify(
+        &self,
+        input: &[ElGamalCard],
+        output: &[ElGamalCard],
+        key: &PublicKey,
+    ) -> Result<(), ShuffleError> {
+        let input = ciphertexts(input)?;
+        let output = ciphertexts(output)?;
+        if input.len() != output.len() || self.rounds.len() != Self::ROUNDS {
+            return Err(ShuffleError::Length);
+        }
+        let commitments: Vec<&[Ciphertext]> = self
+            .rounds
+            .iter()
+            .map(|round| round.commitment.as_slice())
+            .collect();
+        let challenge = challenge(&input, &output, &commitments, key);
+        for (idx, round) in self.rounds.iter().enumerate() {
+            let (source, target) = match challenge_bit(&challenge, idx) {
+                false => (input.as_slice(), round.commitment.as_slice()),
+                true => (round.commitment.as_slice(), output.as_slice()),
+            };
+            if !round.opening.is_valid(input.len())
+                || target.len() != input.len()
+                || round.opening.apply(source, key) != target
+            {
+                return Err(ShuffleError::InvalidProof(idx));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Re-masks all `cards` under `key` and puts them into a random order. Every card has to
+/// be masked under `key` already.
+pub fn shuffle(
+    cards: &[ElGamalCard],
+    key: &PublicKey,
+) -> Result<(Vec<ElGamalCard>, ShuffleProof), ShuffleError> {
+    let input = ciphertexts(cards)?;
+    let n = input.len();
+    let shuffle = Permutation::random(n);
+    let output = shuffle.apply(&input, key);
+    let secrets: Vec<Permutation> = (0..ShuffleProof::ROUNDS)
+        .map(|_| Permutation::random(n))
+        .collect();
+    let commitments: Vec<Vec<Ciphertext>> = secrets
+        .iter()
+        .map(|secret| secret.apply(&input, key))
+        .collect();
+    let challenge = challenge(
+        &input,
+        &output,
+        &commitments.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        key,
+    );
+    let rounds = secrets
+        .into_iter()
+        .zip(commitments)
+        .enumerate()
+        .map(|(idx, (secret, commitment))| {
+            let opening = match challenge_bit(&challenge, idx) {
+                false => secret,
+                true => {
+                    // Output card `i` is input card `shuffle[i]`, which is commitment card `k`
+                    // with `secret[k] == shuffle[i]`
+                    let mut position = vec![0; n];
+                    for (k, idx) in secret.permutation.iter().enumerate() {
+                        position[*idx] = k;
+                    }
+                    let permutation: Vec<usize> = shuffle
+                        .permutation
+                        .iter()
+                        .map(|idx| position[*idx])
+                        .collect();
+                    let randomness = shuffle
+                        .randomness
+                        .iter()
+                        .zip(&permutation)
+                        .map(|(r, k)| r - secret.randomness[*k])
+                        .collect();
+                    Permutation {
+                        permutation,
+                        randomness,
+                    }
+                }
+            };
+            Round {
+                commitment,
+                opening,
+            }
+        })
+        .collect();
+    let output = output
+        .into_iter()
+        .map(|ciphertext| ElGamalCard::Masked(Box::new(ciphertext)))
+        .collect();
+    Ok((output, ShuffleProof { rounds }))
+}
+
+fn ciphertexts(cards: &[ElGamalCard]) -> Result<Vec<Ciphertext>, ShuffleError> {
+    cards
+        .iter()
+        .enumerate()
+        .map(|(idx, card)| match card {
+            ElGamalCard::Masked(ciphertext) => Ok(**ciphertext),
+            ElGamalCard::Open(_) => Err(ShuffleError::NotMasked(idx)),
+        })
+        .collect()
+}
+
+fn challenge(
+    input: &[Ciphertext],
+    output: &[Ciphertext],
+    commitments: &[&[Ciphertext]],
+    key: &PublicKey,
+) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(b"mcg_visual shuffle");
+    hasher.update(key.0.compress().as_bytes());
+    for ciphertext in input
+        .iter()
+        .chain(output)
+        .chain(commitments.concat().iter())
+    {
+        hasher.update(ciphertext.to_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn challenge_bit(challenge: &[u8; 64], round: usize) -> bool {
+    challenge[round / 8] >> (round % 8) & 1 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::elgamal::SecretKey;
+    use crate::game::card::CardEncoding;
+
+    fn deck(key: &SecretKey, types: usize) -> Vec<ElGamalCard> {
+        (0..types)
+            .map(|t| ElGamalCard::Open(t).mask(&key.public()))
+            .collect()
+    }
+
+    #[test]
+    fn honest_shuffle_verifies() {
+        let key = SecretKey::random();
+        let input = deck(&key, 5);
+        let (output, proof) = shuffle(&input, &key.public()).unwrap();
+        assert_eq!(proof.verify(&input, &output, &key.public()), Ok(()));
+        let mut types: Vec<usize> = output
+            .into_iter()
+            .filter_map(|card| card.open(&key).t())
+            .collect();
+        types.sort();
+        assert_eq!(types, (0..5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn changed_output_is_rejected() {
+        let key = SecretKey::random();
+        let input = deck(&key, 5);
+        let (mut output, proof) = shuffle(&input, &key.public()).unwrap();
+        // Another type than any of the input's
+        output[0] = ElGamalCard::Open(7).mask(&key.public());
+        assert!(matches!(
+            proof.verify(&input, &output, &key.public()),
+            Err(ShuffleError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn swapped_output_is_rejected() {
+        let key = SecretKey::random();
+        let input = deck(&key, 5);
+        let (mut output, proof) = shuffle(&input, &key.public()).unwrap();
+        output.swap(0, 1);
+        assert!(matches!(
+            proof.verify(&input, &output, &key.public()),
+            Err(ShuffleError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn swapped_opening_is_rejected() {
+        let key = SecretKey::random();
+        let input = deck(&key, 5);
+        let (output, mut proof) = shuffle(&input, &key.public()).unwrap();
+        proof.rounds[3].opening.permutation.swap(0, 1);
+        assert_eq!(
+            proof.verify(&input, &output, &key.public()),
+            Err(ShuffleError::InvalidProof(3))
+        );
+    }
+
+    #[test]
+    fn wrong_round_count_is_rejected() {
+        let key = SecretKey::random();
+        let input = deck(&key, 3);
+        let (output, proof) = shuffle(&input, &key.public()).unwrap();
+        let mut short = proof.clone();
+        short.rounds.pop();
+        assert_eq!(
+            short.verify(&input, &output, &key.public()),
+            Err(ShuffleError::Length)
+        );
+        let mut long = proof;
+        long.rounds.push(long.rounds[0].clone());
+        assert_eq!(
+            long.verify(&input, &output, &key.public()),
+            Err(ShuffleError::Length)
+        );
+    }
+
+    #[test]
+    fn open_cards_are_rejected() {
+        let key = SecretKey::random();
+        let mut input = deck(&key, 3);
+        input[1] = ElGamalCard::Open(1);
+        assert_eq!(
+            shuffle(&input, &key.public()).map(|_| ()),
+            Err(ShuffleError::NotMasked(1))
+        );
+    }
+}
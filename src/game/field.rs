@@ -1,10 +1,16 @@
-use crate::game::card::SimpleCard::Open;
-use crate::game::card::{CardConfig, CardEncoding};
+use crate::game::card::bits::{self, EncodingError};
+use crate::game::card::elgamal::{ElGamalCard, PublicKey};
+use crate::game::card::shuffle::{self, ShuffleError, ShuffleProof};
+use crate::game::card::{
+    CardAnnotations, CardConfig, CardEncoding, CardId, Orientation, SimpleCard,
+};
 use crate::game::screen::DNDSelector;
 use crate::sprintln;
-use eframe::emath::{vec2, Rect};
+use eframe::emath::{vec2, Pos2, Rect};
 use egui::{frame, Color32, Sense, Vec2};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Formatter};
 use std::ops::Add;
 use std::rc::Rc;
@@ -29,7 +35,7 @@ impl<E: CardEncoding, C: CardConfig> FieldWidget for SimpleField<E, C> {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum SimpleFieldKind {
     Stack,
     Horizontal,
@@ -38,22 +44,56 @@ pub enum SimpleFieldKind {
 #[allow(dead_code)]
 pub struct SimpleField<E: CardEncoding, C: CardConfig> {
     pub(crate) card_config: Rc<C>,
-    pub(crate) cards: Vec<E>,
+    cards: Vec<E>,
+    /// The id of each card in `cards`
+    ids: Vec<CardId>,
+    /// Counters and markers of the cards that have any
+    annotations: BTreeMap<CardId, CardAnnotations>,
     pub kind: SimpleFieldKind,
     pub margin: i8,
     pub max_cards: usize,
     pub selectable: bool,
     pub draggable: bool,
     max_card_size: Option<Vec2>,
-    // TODO Adjust DNDSelector to a (&SimpleField, Option<Idx>) if possible
-    pub(crate) drag_payload: RefCell<Option<usize>>,
+    pub(crate) drag_payload: RefCell<Option<CardId>>,
     pub(crate) drop_payload: RefCell<Option<usize>>,
+    pub(crate) action_payload: RefCell<Option<(CardId, CardAction)>>,
+    /// Name typed into the context menu for a new counter or marker
+    annotation_input: RefCell<String>,
+}
+/// What the context menu of a card asked for, see [`SimpleField::take_action`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardAction {
+    Rotate(Orientation),
+    /// Turn the card face down or face up
+    Flip,
+    /// Add `.1` to the counter `.0`
+    Counter(String, i64),
+    ToggleMarker(String),
+}
+/// Everything of a [`SimpleField`] but its shared [`CardConfig`], used to persist fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimpleFieldState<E> {
+    pub cards: Vec<E>,
+    /// Missing in saves from before ids existed, the cards get fresh ones then
+    #[serde(default)]
+    pub ids: Vec<CardId>,
+    #[serde(default)]
+    pub annotations: BTreeMap<CardId, CardAnnotations>,
+    pub kind: SimpleFieldKind,
+    pub margin: i8,
+    pub max_cards: usize,
+    pub selectable: bool,
+    pub draggable: bool,
+    pub max_card_size: Option<Vec2>,
 }
 /// Builder
 impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
     pub fn new(card_config: Rc<C>) -> Self {
         Self {
             cards: vec![],
+            ids: vec![],
+            annotations: BTreeMap::new(),
             card_config,
             kind: SimpleFieldKind::Horizontal,
             margin: 4,
@@ -63,11 +103,36 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
             max_card_size: None,
             drag_payload: RefCell::new(None),
             drop_payload: RefCell::new(None),
+            action_payload: RefCell::new(None),
+            annotation_input: RefCell::new(String::new()),
         }
     }
     pub fn from_collection(card_config: Rc<C>, cards: impl IntoIterator<Item = E>) -> Self {
+        let cards: Vec<E> = cards.into_iter().collect();
         SimpleField {
-            cards: cards.into_iter().collect(),
+            ids: cards.iter().map(|_| CardId::fresh()).collect(),
+            cards,
+            ..SimpleField::new(card_config)
+        }
+    }
+    pub fn from_state(card_config: Rc<C>, state: SimpleFieldState<E>) -> Self {
+        let (ids, annotations) = if state.ids.len() == state.cards.len() {
+            state.ids.iter().copied().for_each(CardId::reserve);
+            (state.ids, state.annotations)
+        } else {
+            let ids = state.cards.iter().map(|_| CardId::fresh()).collect();
+            (ids, BTreeMap::new())
+        };
+        SimpleField {
+            cards: state.cards,
+            ids,
+            annotations,
+            kind: state.kind,
+            margin: state.margin,
+            max_cards: state.max_cards,
+            selectable: state.selectable,
+            draggable: state.draggable,
+            max_card_size: state.max_card_size,
             ..SimpleField::new(card_config)
         }
     }
@@ -93,7 +158,7 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
                 .calc_size(max_card_size, Some(self.card_config.natural_size()))
         } else {
             self.card_config
-                .img(&Open(0))
+                .img(&SimpleCard::face_up(0))
                 .calc_size(max_card_size, Some(self.card_config.natural_size()))
         };
         SimpleField {
@@ -114,95 +179,252 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
     pub fn get_cards(&self) -> &Vec<E> {
         &self.cards
     }
+    pub fn ids(&self) -> &[CardId] {
+        &self.ids
+    }
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+    pub fn position(&self, id: CardId) -> Option<usize> {
+        self.ids.iter().position(|card| *card == id)
+    }
+    pub fn contains(&self, id: CardId) -> bool {
+        self.ids.contains(&id)
+    }
+    pub fn get(&self, id: CardId) -> Option<&E> {
+        Some(&self.cards[self.position(id)?])
+    }
+    pub fn get_mut(&mut self, id: CardId) -> Option<&mut E> {
+        let idx = self.position(id)?;
+        Some(&mut self.cards[idx])
+    }
+    /// Counters and markers of the card with `id`, [`None`] if it has none.
+    pub fn annotations(&self, id: CardId) -> Option<&CardAnnotations> {
+        self.annotations.get(&id)
+    }
+    /// [`None`] if there is no card with `id` in this field.
+    pub fn annotations_mut(&mut self, id: CardId) -> Option<&mut CardAnnotations> {
+        if self.contains(id) {
+            Some(self.annotations.entry(id).or_default())
+        } else {
+            None
+        }
+    }
+    pub fn state(&self) -> SimpleFieldState<E>
+    where
+        E: Clone,
+    {
+        SimpleFieldState {
+            cards: self.cards.clone(),
+            ids: self.ids.clone(),
+            annotations: self.annotations.clone(),
+            kind: self.kind,
+            margin: self.margin,
+            max_cards: self.max_cards,
+            selectable: self.selectable,
+            draggable: self.draggable,
+            max_card_size: self.max_card_size,
+        }
+    }
     pub fn is_stack(&self) -> bool {
         matches!(self.kind, SimpleFieldKind::Stack)
     }
     pub fn is_horizontal(&self) -> bool {
         matches!(self.kind, SimpleFieldKind::Horizontal)
     }
-    /// Returns the index of which card got dragged and where a card got dropped.
+    /// Returns the id of the card that got dragged and the index a card got dropped onto.
     ///
     /// E.g.
-    /// ````
+    /// ````no_run
+    /// use mcg_visual::game::card::{DirectoryCardType, SimpleCard};
     /// use mcg_visual::game::field::SimpleField;
-    /// if let (Some(i), None) = SimpleField::get_payload(todo!("valid &self")) {
-    ///     // card at index i was dragged
+    /// type Field = SimpleField<SimpleCard, DirectoryCardType>;
+    /// if let (Some(id), None) = Field::get_payload(todo!("valid &self")) {
+    ///     // card with this id was dragged
     ///     todo!("Handle dragged Card");
     /// }
-    /// if let (None, Some(j)) = SimpleField::get_payload(todo!("valid &self")) {
+    /// if let (None, Some(j)) = Field::get_payload(todo!("valid &self")) {
     ///     // card was dropped onto index j
     ///     todo!("Handle dropped Card");
     /// }
     /// ````
-    pub fn get_payload(&self) -> (Option<usize>, Option<usize>) {
+    pub fn get_payload(&self) -> (Option<CardId>, Option<usize>) {
         let drag = self.drag_payload.replace(None);
         let drop = self.drop_payload.replace(None);
         (drag, drop)
     }
+    /// Returns what the context menu of a card asked for since the last call.
+    pub fn take_action(&self) -> Option<(CardId, CardAction)> {
+        self.action_payload.replace(None)
+    }
+    /// Size a card takes up on the table, sideways cards swap width and height.
+    pub fn footprint(&self, card: &E) -> Vec2 {
+        let size = self.get_card_size();
+        if card.orientation().is_sideways() {
+            vec2(size.y, size.x)
+        } else {
+            size
+        }
+    }
+    /// Paints `card` turned by its [`Orientation`], filling `rect` of its
+    /// [`SimpleField::footprint`].
+    pub fn paint_card(&self, ui: &egui::Ui, card: &E, rect: Rect) {
+        let rect = Rect::from_center_size(rect.center(), self.get_card_size());
+        self.card_config.paint(ui, card, rect);
+    }
 }
 /// Utility
 impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
-    pub fn push(&mut self, card: E) {
+    /// The cards as bytes, see [`bits`](crate::game::card::bits) for the layout.
+    pub fn pack(&self) -> Result<Vec<u8>, EncodingError> {
+        bits::pack(&self.cards, self.card_config.as_ref())
+    }
+    /// Replaces the cards with the ones packed into `bytes`. They're left as they are
+    /// if any card isn't valid for this field's [`CardConfig`].
+    pub fn unpack(&mut self, bytes: &[u8]) -> Result<(), EncodingError> {
+        self.cards = bits::unpack(bytes, self.card_config.as_ref())?;
+        self.ids = self.cards.iter().map(|_| CardId::fresh()).collect();
+        self.annotations.clear();
+        Ok(())
+    }
+    /// Adds a new card on top and returns its id.
+    pub fn push(&mut self, card: E) -> CardId {
+        let id = CardId::fresh();
+        self.ids.push(id);
         self.cards.push(card);
+        id
+    }
+    /// Takes the card at `idx` out, together with its id to [`SimpleField::insert`] it elsewhere.
+    /// Its annotations are dropped, [`SimpleField::take_annotations`] them first to keep them.
+    pub fn remove(&mut self, idx: usize) -> (CardId, E) {
+        let id = self.ids.remove(idx);
+        self.annotations.remove(&id);
+        (id, self.cards.remove(idx))
     }
-    pub fn remove(&mut self, idx: usize) -> E {
-        self.cards.remove(idx)
+    pub fn remove_id(&mut self, id: CardId) -> Option<(CardId, E)> {
+        Some(self.remove(self.position(id)?))
     }
-    pub fn pop(&mut self) -> Option<E> {
-        self.cards.pop()
+    pub fn pop(&mut self) -> Option<(CardId, E)> {
+        let id = self.ids.pop()?;
+        self.annotations.remove(&id);
+        Some((id, self.cards.pop()?))
     }
-    pub fn insert(&mut self, idx: usize, card: E) {
-        if idx >= self.cards.len() {
-            self.cards.push(card);
+    pub fn take_annotations(&mut self, id: CardId) -> CardAnnotations {
+        self.annotations.remove(&id).unwrap_or_default()
+    }
+    /// Replaces the annotations of the card with `id`, ignored if it isn't in this field.
+    pub fn set_annotations(&mut self, id: CardId, annotations: CardAnnotations) {
+        if !self.contains(id) {
+            return;
+        }
+        if annotations.is_empty() {
+            self.annotations.remove(&id);
         } else {
-            self.cards.insert(idx, card);
+            self.annotations.insert(id, annotations);
+        }
+    }
+    /// Inserts a card that keeps the id it had before, e.g. in another field.
+    pub fn insert(&mut self, idx: usize, (id, card): (CardId, E)) {
+        let idx = idx.min(self.cards.len());
+        self.ids.insert(idx, id);
+        self.cards.insert(idx, card);
+    }
+}
+impl<C: CardConfig> SimpleField<SimpleCard, C> {
+    /// Applies an action [`SimpleField::take_action`] returned, if the card is still here.
+    pub fn apply_action(&mut self, (id, action): (CardId, CardAction)) {
+        let Some(card) = self.get_mut(id) else {
+            return;
+        };
+        match action {
+            CardAction::Rotate(orientation) => {
+                let _ = card.set_orientation(orientation);
+            }
+            CardAction::Flip => *card = card.clone().flip(),
+            CardAction::Counter(name, delta) => {
+                let mut annotations = self.take_annotations(id);
+                annotations.add_counter(name, delta);
+                self.set_annotations(id, annotations);
+            }
+            CardAction::ToggleMarker(marker) => {
+                let mut annotations = self.take_annotations(id);
+                annotations.toggle_marker(marker);
+                self.set_annotations(id, annotations);
+            }
         }
     }
 }
+impl<C: CardConfig> SimpleField<ElGamalCard, C> {
+    /// Re-masks and permutes all cards, see [`shuffle`](crate::game::card::shuffle) for how
+    /// other players check the returned proof against the previous cards. Annotations are
+    /// dropped, they would tell where a card went.
+    pub fn shuffle(&mut self, key: &PublicKey) -> Result<ShuffleProof, ShuffleError> {
+        let (cards, proof) = shuffle::shuffle(&self.cards, key)?;
+        self.cards = cards;
+        self.ids = self.cards.iter().map(|_| CardId::fresh()).collect();
+        self.annotations.clear();
+        Ok(proof)
+    }
+}
 /// Internal
 impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
-    fn set_drag_payload(&self, response: &egui::Response, payload: usize) {
-        // TODO Make the payload be a unique identifier
-        response.dnd_set_drag_payload(DNDSelector::Index(payload));
-        self.drag_payload.replace(Some(payload));
+    fn set_drag_payload(&self, response: &egui::Response, idx: usize) {
+        let id = self.ids[idx];
+        response.dnd_set_drag_payload(DNDSelector::Card(id));
+        self.drag_payload.replace(Some(id));
     }
+    /// `payload` is the index the dropped card should get, it's no card yet.
     fn set_drop_payload(&self, response: &egui::Response, payload: usize) {
-        // TODO Make the payload be a unique identifier
         if response.dnd_release_payload::<DNDSelector>().is_some() {
             self.drop_payload.replace(Some(payload));
         }
     }
     fn horizontal_card_selection(&self, ui: &egui::Ui) -> Option<usize> {
-        let pointer_pos = ui.input(|state| state.pointer.latest_pos());
+        let pointer_pos = ui.input(|state| state.pointer.latest_pos())?;
         let rect = ui.min_rect();
-        if pointer_pos.is_some() && rect.contains(pointer_pos.unwrap()) {
+        if rect.contains(pointer_pos) {
             let max = if self.cards.len() > self.max_cards {
                 rect.right() - rect.left()
             } else {
                 self.cards.len() as f32 * (self.get_card_size().x + self.margin as f32)
                     - self.margin as f32
             };
-            Some((self.cards.len() as f32 * (pointer_pos.unwrap().x - rect.left()) / max) as usize)
+            Some((self.cards.len() as f32 * (pointer_pos.x - rect.left()) / max) as usize)
         } else {
             None
         }
     }
-    fn horizontal_drag_size(&self) -> Vec2 {
-        let mut size = self.get_card_size();
-        size.x = self.card_pos(1).x - self.card_pos(0).x;
-        size
+    /// Footprint of the card at `idx`, slots behind the last card are upright.
+    fn slot_size(&self, idx: usize) -> Vec2 {
+        match self.cards.get(idx) {
+            Some(card) => self.footprint(card),
+            None => self.get_card_size(),
+        }
+    }
+    /// Width of the hit area of the card at `idx`, up to where the next card starts.
+    fn horizontal_drag_width(&self, idx: usize) -> f32 {
+        self.card_pos(idx + 1).x - self.card_pos(idx).x
     }
     fn content_size(&self) -> Vec2 {
         match self.kind {
-            SimpleFieldKind::Stack => self
-                .get_card_size()
-                .add(vec2(self.max_cards as f32, self.max_cards as f32)),
+            SimpleFieldKind::Stack => {
+                let size = self
+                    .cards
+                    .iter()
+                    .map(|card| self.footprint(card))
+                    .fold(self.get_card_size(), Vec2::max);
+                size.add(vec2(self.max_cards as f32, self.max_cards as f32))
+            }
             SimpleFieldKind::Horizontal => self.get_card_size().add(vec2(
                 (self.max_cards as f32 - 1.0) * (self.get_card_size().x + self.margin as f32),
                 self.margin as f32,
             )),
         }
     }
+    /// Top left corner of the card at `idx`, relative to the field's origin.
     fn card_pos(&self, idx: usize) -> Vec2 {
         match self.kind {
             SimpleFieldKind::Stack => {
@@ -214,48 +436,152 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
                 Vec2::new(x, -x)
             }
             SimpleFieldKind::Horizontal => {
+                let natural_x = |idx: usize| -> f32 {
+                    (0..idx)
+                        .map(|idx| self.slot_size(idx).x + self.margin as f32)
+                        .sum()
+                };
                 let cards = self.cards.len();
-                let x = if cards <= self.max_cards {
-                    (self.get_card_size().x + self.margin as f32) * (idx as f32)
+                let total = natural_x(cards) - self.margin as f32;
+                let available = self.content_size().x;
+                // Overlap the cards evenly once they don't fit next to each other
+                let x = if cards <= 1 || total <= available {
+                    natural_x(idx)
                 } else {
-                    (self.get_card_size().x + self.margin as f32)
-                        * (idx as f32)
-                        * ((self.max_cards - 1) as f32)
-                        / ((cards - 1) as f32)
+                    natural_x(idx) * (available - self.slot_size(cards - 1).x)
+                        / natural_x(cards - 1)
                 };
-                Vec2::new(x, 0.0)
+                // Sideways cards are centered on the row
+                let y = (self.get_card_size().y - self.slot_size(idx).y) / 2.0;
+                Vec2::new(x, y)
             }
         }
     }
+    fn card_rect(&self, origin: Pos2, idx: usize) -> Rect {
+        Rect::from_min_size(origin.add(self.card_pos(idx)), self.slot_size(idx))
+    }
+    /// Entries of a card's context menu, their [`CardAction`]s go to the action payload.
+    fn card_menu(&self, ui: &mut egui::Ui, idx: usize) {
+        let id = self.ids[idx];
+        let card = &self.cards[idx];
+        let orientation = card.orientation();
+        let tap = if orientation == Orientation::Upright {
+            "Tap"
+        } else {
+            "Untap"
+        };
+        if ui.button(tap).clicked() {
+            let action = CardAction::Rotate(orientation.toggle_tapped());
+            self.action_payload.replace(Some((id, action)));
+            ui.close_menu();
+        }
+        if orientation != Orientation::UpsideDown && ui.button("Turn upside down").clicked() {
+            let action = CardAction::Rotate(Orientation::UpsideDown);
+            self.action_payload.replace(Some((id, action)));
+            ui.close_menu();
+        }
+        let flip = if card.is_open() {
+            "Flip face down"
+        } else {
+            "Flip face up"
+        };
+        if ui.button(flip).clicked() {
+            self.action_payload.replace(Some((id, CardAction::Flip)));
+            ui.close_menu();
+        }
+        ui.separator();
+        self.annotation_menu(ui, id);
+    }
+    /// Counters can be changed with the menu kept open, everything else closes it.
+    fn annotation_menu(&self, ui: &mut egui::Ui, id: CardId) {
+        let annotations = self.annotations(id).cloned().unwrap_or_default();
+        for (name, value) in &annotations.counters {
+            ui.horizontal(|ui| {
+                ui.label(format!("{name}: {value}"));
+                if ui.small_button("-").clicked() {
+                    let action = CardAction::Counter(name.clone(), -1);
+                    self.action_payload.replace(Some((id, action)));
+                }
+                if ui.small_button("+").clicked() {
+                    let action = CardAction::Counter(name.clone(), 1);
+                    self.action_payload.replace(Some((id, action)));
+                }
+            });
+        }
+        for marker in &annotations.markers {
+            if ui.button(format!("Remove {marker}")).clicked() {
+                let action = CardAction::ToggleMarker(marker.clone());
+                self.action_payload.replace(Some((id, action)));
+                ui.close_menu();
+            }
+        }
+        let mut input = self.annotation_input.borrow_mut();
+        ui.add(egui::TextEdit::singleline(&mut *input).hint_text("Counter or marker"));
+        let name = input.trim().to_string();
+        ui.horizontal(|ui| {
+            let valid = !name.is_empty();
+            if ui
+                .add_enabled(valid, egui::Button::new("Add counter"))
+                .clicked()
+            {
+                let action = CardAction::Counter(name.clone(), 1);
+                self.action_payload.replace(Some((id, action)));
+                input.clear();
+                ui.close_menu();
+            }
+            let valid = valid && !annotations.has_marker(&name);
+            if ui
+                .add_enabled(valid, egui::Button::new("Add marker"))
+                .clicked()
+            {
+                let action = CardAction::ToggleMarker(name.clone());
+                self.action_payload.replace(Some((id, action)));
+                input.clear();
+                ui.close_menu();
+            }
+        });
+    }
+    /// Stacks a badge per counter and marker of the card at `idx` into its top left corner.
+    fn paint_badges(&self, ui: &egui::Ui, idx: usize, rect: Rect) {
+        let Some(annotations) = self.annotations(self.ids[idx]) else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(rect);
+        let mut pos = rect.left_top().add(vec2(4.0, 4.0));
+        for badge in annotations.badges() {
+            let galley =
+                painter.layout_no_wrap(badge, egui::FontId::proportional(11.0), Color32::WHITE);
+            let badge_rect = Rect::from_min_size(pos, galley.size()).expand(2.0);
+            painter.rect_filled(badge_rect, 4.0, Color32::from_black_alpha(180));
+            painter.galley(pos, galley, Color32::WHITE);
+            pos.y += badge_rect.height() + 2.0;
+        }
+    }
     fn draw_stack(&self, ui: &mut egui::Ui) -> egui::Response {
         ui.set_min_size(self.content_size());
         let origin = ui.cursor().left_top().add(vec2(0.0, self.max_cards as f32));
         for (idx, card) in self.cards.iter().enumerate() {
-            self.card_config.img(card).paint_at(
-                ui,
-                Rect::from_min_size(origin.add(self.card_pos(idx)), self.get_card_size()),
-            );
+            let rect = self.card_rect(origin, idx);
+            self.paint_card(ui, card, rect);
+            self.paint_badges(ui, idx, rect);
         }
         if self.draggable && !self.cards.is_empty() {
+            let top = self.cards.len() - 1;
+            let top_rect = self.card_rect(origin, top);
             ui.allocate_new_ui(
                 egui::UiBuilder::new()
                     .sense(Sense::click_and_drag())
-                    .max_rect(Rect::from_min_size(
-                        origin.add(self.card_pos(self.cards.len() - 1)),
-                        self.get_card_size(),
-                    )),
+                    .max_rect(top_rect),
                 |ui| {
-                    ui.set_min_size(self.get_card_size());
+                    ui.set_min_size(top_rect.size());
                     if ui.response().drag_started() {
-                        self.set_drag_payload(&ui.response(), self.cards.len() - 1);
+                        self.set_drag_payload(&ui.response(), top);
                     }
                     self.set_drop_payload(&ui.response(), self.cards.len());
                     ui.response().context_menu(|ui| {
+                        self.card_menu(ui, top);
                         if ui.button("Show inner").clicked() {
-                            sprintln!(
-                                "Imagine the hided information of card {idx} here",
-                                idx = self.cards.len() - 1
-                            );
+                            sprintln!("Imagine the hided information of card {top} here");
                             ui.close_menu();
                         }
                     });
@@ -271,32 +597,36 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
         // TODO show card on mouse hover if obstructed
         let _selection: Option<usize> = self.horizontal_card_selection(ui);
         for (idx, card) in self.cards.iter().enumerate() {
-            self.card_config.img(card).paint_at(
-                ui,
-                Rect::from_min_size(origin.add(self.card_pos(idx)), self.get_card_size()),
-            );
+            let rect = self.card_rect(origin, idx);
+            self.paint_card(ui, card, rect);
+            self.paint_badges(ui, idx, rect);
             if self.draggable {
+                let drag_rect = Rect::from_min_size(
+                    rect.min,
+                    vec2(self.horizontal_drag_width(idx), rect.height()),
+                );
                 ui.allocate_new_ui(
                     egui::UiBuilder::new()
                         .sense(Sense::click_and_drag())
-                        .max_rect(Rect::from_min_size(
-                            origin.add(self.card_pos(idx)),
-                            self.horizontal_drag_size(),
-                        )),
+                        .max_rect(drag_rect),
                     |ui| {
-                        ui.set_min_size(self.horizontal_drag_size());
+                        ui.set_min_size(drag_rect.size());
                         if ui.response().drag_started() {
                             self.set_drag_payload(&ui.response(), idx);
                         }
-                        self.set_drop_payload(&ui.response(), idx)
+                        self.set_drop_payload(&ui.response(), idx);
+                        ui.response().context_menu(|ui| self.card_menu(ui, idx));
                     },
                 );
             }
         }
-        if self.draggable {
+        if self.draggable && !self.cards.is_empty() {
+            let last = self.cards.len() - 1;
+            let last_rect = self.card_rect(origin, last);
             let last_drag_rect_min = origin.add(self.card_pos(self.cards.len()));
-            let mut last_drag_rect_size = self.get_card_size();
-            last_drag_rect_size.x -= self.horizontal_drag_size().x;
+            let last_drag_rect_min = Pos2::new(last_drag_rect_min.x, last_rect.top());
+            let mut last_drag_rect_size = last_rect.size();
+            last_drag_rect_size.x -= self.horizontal_drag_width(last);
             ui.allocate_new_ui(
                 egui::UiBuilder::new()
                     .sense(Sense::click_and_drag())
@@ -304,11 +634,13 @@ impl<E: CardEncoding, C: CardConfig> SimpleField<E, C> {
                 |ui| {
                     ui.set_min_size(last_drag_rect_size);
                     if ui.response().drag_started() {
-                        self.set_drag_payload(&ui.response(), self.cards.len() - 1);
+                        self.set_drag_payload(&ui.response(), last);
                     }
                     self.set_drop_payload(&ui.response(), self.cards.len())
                 },
             );
+        }
+        if self.draggable {
             self.set_drop_payload(&ui.response(), self.cards.len());
         }
         ui.response()
@@ -324,3 +656,70 @@ impl<E: CardEncoding + Debug, C: CardConfig + Debug> Debug for SimpleField<E, C>
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::DirectoryCardType;
+
+    fn field(types: usize) -> SimpleField<SimpleCard, DirectoryCardType> {
+        let names = (0..types).map(|t| format!("{t:02}.png")).collect();
+        let config = DirectoryCardType::new("test".to_string(), names, egui::vec2(1.0, 1.0));
+        SimpleField::from_collection(Rc::new(config), (0..types).map(SimpleCard::face_up))
+    }
+    fn types(field: &SimpleField<SimpleCard, DirectoryCardType>) -> Vec<Option<usize>> {
+        field.get_cards().iter().map(CardEncoding::t).collect()
+    }
+
+    #[test]
+    fn ids_follow_their_cards() {
+        let mut source = field(4);
+        let mut target = field(0);
+        let ids = source.ids().to_vec();
+        let (id, card) = source.remove(1);
+        assert_eq!((id, card.t()), (ids[1], Some(1)));
+        target.insert(5, (id, card));
+        let top = target.push(SimpleCard::face_up(3));
+        assert_eq!(target.ids(), &[ids[1], top]);
+        assert_eq!(target.get(ids[1]), Some(&SimpleCard::face_up(1)));
+        assert_eq!(source.ids(), &[ids[0], ids[2], ids[3]]);
+        assert_eq!(types(&source), vec![Some(0), Some(2), Some(3)]);
+        assert_eq!(source.pop().map(|(id, _)| id), Some(ids[3]));
+        assert_eq!(
+            source.remove_id(ids[0]).map(|(_, card)| card.t()),
+            Some(Some(0))
+        );
+        assert_eq!(source.get(ids[0]), None);
+        assert_eq!(source.ids().len(), source.len());
+    }
+
+    #[test]
+    fn annotations_stay_with_their_card() {
+        let mut source = field(3);
+        let mut target = field(0);
+        let id = source.ids()[2];
+        source.apply_action((id, CardAction::Counter("damage".to_string(), 2)));
+        let annotations = source.take_annotations(id);
+        let card = source.remove_id(id).unwrap();
+        target.insert(0, card);
+        target.set_annotations(id, annotations);
+        assert_eq!(target.annotations(id).unwrap().counter("damage"), 2);
+        assert!(source.annotations(id).is_none());
+    }
+
+    #[test]
+    fn unpacked_cards_get_fresh_ids() {
+        let mut field = field(3);
+        let before = field.ids().to_vec();
+        let bytes = field.pack().unwrap();
+        field.unpack(&bytes).unwrap();
+        assert_eq!(types(&field), vec![Some(0), Some(1), Some(2)]);
+        assert_eq!(field.ids().len(), 3);
+        assert!(field.ids().iter().all(|id| !before.contains(id)));
+        // Broken bytes leave cards and ids alone
+        let ids = field.ids().to_vec();
+        assert!(field.unpack(&bytes[..3]).is_err());
+        assert_eq!(field.ids(), ids.as_slice());
+        assert_eq!(field.len(), 3);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::game::navigation::{Location, Navigator, TransitionKind};
+use crate::sprintln;
+use egui::Context;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Mirrors the current [`Location`] into `location.hash` as `#/<route>?<params>` and
+/// turns `hashchange` events (links, browser back/forward) into navigation requests.
+///
+/// Route, keys and values are percent-encoded, so they may contain `?`, `&` and `=`.
+pub(crate) struct HashRouter {
+    changed: Rc<RefCell<Option<Location>>>,
+    /// Browser history entries before the current one that belong to this page load
+    behind: Cell<usize>,
+    /// Browser history entries after the current one, left by going back
+    ahead: Cell<usize>,
+    _listener: Closure<dyn FnMut()>,
+}
+impl HashRouter {
+    pub(crate) fn new(ctx: &Context) -> Option<Self> {
+        let window = web_sys::window()?;
+        let changed = Rc::new(RefCell::new(None));
+        let listener = {
+            let changed = Rc::clone(&changed);
+            let ctx = ctx.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                changed.replace(Self::read_hash());
+                ctx.request_repaint();
+            })
+        };
+        window
+            .add_event_listener_with_callback("hashchange", listener.as_ref().unchecked_ref())
+            .ok()?;
+        Some(Self {
+            changed,
+            behind: Cell::new(0),
+            ahead: Cell::new(0),
+            _listener: listener,
+        })
+    }
+    /// The location the page got opened with, `None` without a `#/...` hash.
+    pub(crate) fn initial_location() -> Option<Location> {
+        Self::read_hash()
+    }
+    /// Turns a hash change since the last frame into a request on `navigator`.
+    pub(crate) fn poll(&self, navigator: &mut Navigator) {
+        let Some(location) = self.changed.take() else {
+            return;
+        };
+        if &location == navigator.current() {
+            // Caused by our own `sync`
+        } else if navigator.peek_back() == Some(&location) {
+            navigator.back();
+        } else if navigator.peek_forward() == Some(&location) {
+            navigator.forward();
+        } else {
+            navigator.push_location(location);
+        }
+    }
+    /// Writes `location` into the address bar, moving through the browser history the way
+    /// the [`Navigator`] moved, so the browser's back button stays in step with the app's.
+    ///
+    /// Back and forward only use the browser history where it has entries of this page
+    /// load, otherwise they replace the current entry instead of leaving the app.
+    pub(crate) fn sync(&self, location: &Location, kind: TransitionKind) {
+        // Moves the browser made itself, e.g. its back button, got polled already
+        let browser_moved = Self::read_hash().as_ref() == Some(location);
+        let history = match kind {
+            TransitionKind::Push => {
+                self.behind.set(self.behind.get() + 1);
+                self.ahead.set(0);
+                None
+            }
+            TransitionKind::Back if self.behind.get() > 0 => {
+                self.behind.set(self.behind.get() - 1);
+                self.ahead.set(self.ahead.get() + 1);
+                Some(-1)
+            }
+            TransitionKind::Forward if self.ahead.get() > 0 => {
+                self.ahead.set(self.ahead.get() - 1);
+                self.behind.set(self.behind.get() + 1);
+                Some(1)
+            }
+            _ => None,
+        };
+        if browser_moved {
+            return;
+        }
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let hash = Self::format_hash(location);
+        let result = match (kind, history) {
+            (_, Some(delta)) => window
+                .history()
+                .and_then(|history| history.go_with_delta(delta)),
+            (TransitionKind::Push, _) => window.location().set_hash(&hash),
+            _ => window.history().and_then(|history| {
+                history.replace_state_with_url(&JsValue::NULL, "", Some(&hash))
+            }),
+        };
+        if let Err(err) = result {
+            sprintln!("Failed to update location hash: {err:?}");
+        }
+    }
+    fn read_hash() -> Option<Location> {
+        let hash = web_sys::window()?.location().hash().ok()?;
+        hash.strip_prefix("#/")
+            .filter(|route| !route.is_empty())
+            .and_then(|route| route.parse().ok())
+    }
+    fn format_hash(location: &Location) -> String {
+        format!("#/{location}")
+    }
+}
@@ -0,0 +1,259 @@
+use egui::{Context, Id, Key, Modifiers};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModalKind {
+    Confirm,
+    /// Asks for a line of text, starting with the given one
+    Prompt(String),
+    /// Asks to pick one of the options, starting with the given index
+    Choice {
+        options: Vec<String>,
+        selected: usize,
+    },
+}
+
+/// How a [`Modal`] got closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModalResponse {
+    Confirmed,
+    Cancelled,
+    Text(String),
+    Choice(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Modal {
+    pub title: String,
+    pub message: String,
+    pub kind: ModalKind,
+    confirm_label: String,
+    cancel_label: String,
+}
+/// Builder
+impl Modal {
+    pub fn confirm(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::Confirm,
+            confirm_label: String::from("OK"),
+            cancel_label: String::from("Cancel"),
+        }
+    }
+    pub fn prompt(
+        title: impl Into<String>,
+        message: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind: ModalKind::Prompt(text.into()),
+            ..Self::confirm(title, message)
+        }
+    }
+    pub fn choice(
+        title: impl Into<String>,
+        message: impl Into<String>,
+        options: Vec<String>,
+    ) -> Self {
+        Self {
+            kind: ModalKind::Choice {
+                options,
+                selected: 0,
+            },
+            ..Self::confirm(title, message)
+        }
+    }
+    pub fn confirm_label(self, confirm_label: impl Into<String>) -> Self {
+        Modal {
+            confirm_label: confirm_label.into(),
+            ..self
+        }
+    }
+    pub fn cancel_label(self, cancel_label: impl Into<String>) -> Self {
+        Modal {
+            cancel_label: cancel_label.into(),
+            ..self
+        }
+    }
+}
+/// Internal
+impl Modal {
+    fn confirmed(&self) -> ModalResponse {
+        match &self.kind {
+            ModalKind::Confirm => ModalResponse::Confirmed,
+            ModalKind::Prompt(text) => ModalResponse::Text(text.clone()),
+            ModalKind::Choice { selected, .. } => ModalResponse::Choice(*selected),
+        }
+    }
+    fn ui(&mut self, ui: &mut egui::Ui) -> Option<ModalResponse> {
+        let mut response = None;
+        ui.set_width(300.0);
+        ui.heading(&self.title);
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+        match &mut self.kind {
+            ModalKind::Confirm => {}
+            ModalKind::Prompt(text) => {
+                ui.add_space(5.0);
+                ui.text_edit_singleline(text).request_focus();
+            }
+            ModalKind::Choice { options, selected } => {
+                ui.add_space(5.0);
+                for (idx, option) in options.iter().enumerate() {
+                    ui.radio_value(selected, idx, option);
+                }
+            }
+        }
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            if ui.button(&self.confirm_label).clicked() {
+                response = Some(self.confirmed());
+            }
+            if ui.button(&self.cancel_label).clicked() {
+                response = Some(ModalResponse::Cancelled);
+            }
+        });
+        response
+    }
+}
+
+/// Modal dialogs on top of the current screen, owned by the [`App`](crate::game::App).
+///
+/// While a modal is open, the screen below doesn't get any input. Screens open a modal
+/// under an id and poll [`ModalLayer::take_response`] with that id in later frames.
+#[derive(Default)]
+pub struct ModalLayer {
+    stack: Vec<(String, Modal)>,
+    responses: HashMap<String, ModalResponse>,
+}
+impl ModalLayer {
+    pub fn new() -> Self {
+        Self {
+            stack: vec![],
+            responses: HashMap::new(),
+        }
+    }
+    /// Opens `modal` on top of all others. An open modal with the same id gets replaced.
+    pub fn open(&mut self, id: impl Into<String>, modal: Modal) {
+        let id = id.into();
+        self.stack.retain(|(open, _)| *open != id);
+        self.responses.remove(&id);
+        self.stack.push((id, modal));
+    }
+    /// Returns how the modal with `id` got closed, once.
+    pub fn take_response(&mut self, id: &str) -> Option<ModalResponse> {
+        self.responses.remove(id)
+    }
+    pub fn is_open(&self) -> bool {
+        !self.stack.is_empty()
+    }
+    pub fn is_open_with(&self, id: &str) -> bool {
+        self.stack.iter().any(|(open, _)| open == id)
+    }
+}
+/// Internal
+impl ModalLayer {
+    /// Shows the topmost modal. Enter confirms it, Escape or a click beside it cancels it.
+    pub(crate) fn show(&mut self, ctx: &Context) {
+        let Some((id, modal)) = self.stack.last_mut() else {
+            return;
+        };
+        let shown = egui::Modal::new(Id::new(("modal", id.as_str()))).show(ctx, |ui| modal.ui(ui));
+        let should_close = shown.should_close();
+        let mut response = shown.inner;
+        if response.is_none() && ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter)) {
+            response = Some(modal.confirmed());
+        }
+        if response.is_none() && should_close {
+            response = Some(ModalResponse::Cancelled);
+        }
+        if let Some(response) = response {
+            let (id, _) = self.stack.pop().unwrap();
+            self.responses.insert(id, response);
+        }
+    }
+    /// Responses nobody picked up belong to a screen that isn't shown anymore.
+    pub(crate) fn screen_changed(&mut self) {
+        self.stack.clear();
+        self.responses.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, RawInput};
+
+    /// Shows the layer for a frame in which `key` gets pressed.
+    fn frame(ctx: &Context, layer: &mut ModalLayer, key: Option<Key>) {
+        let events = key
+            .into_iter()
+            .map(|key| Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            })
+            .collect();
+        let input = RawInput {
+            events,
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| layer.show(ctx));
+    }
+
+    #[test]
+    fn responses_are_taken_once() {
+        let ctx = Context::default();
+        let mut layer = ModalLayer::new();
+        layer.open("quit", Modal::confirm("Quit?", ""));
+        frame(&ctx, &mut layer, None);
+        assert!(layer.is_open_with("quit"));
+        assert_eq!(layer.take_response("quit"), None);
+        frame(&ctx, &mut layer, Some(Key::Enter));
+        assert!(!layer.is_open());
+        assert_eq!(layer.take_response("other"), None);
+        assert_eq!(layer.take_response("quit"), Some(ModalResponse::Confirmed));
+        assert_eq!(layer.take_response("quit"), None);
+    }
+
+    #[test]
+    fn topmost_modal_answers_first() {
+        let ctx = Context::default();
+        let mut layer = ModalLayer::new();
+        let options = vec![String::from("a"), String::from("b")];
+        layer.open("pick", Modal::choice("Pick", "", options));
+        layer.open("name", Modal::prompt("Name", "", "Jan"));
+        frame(&ctx, &mut layer, None);
+        frame(&ctx, &mut layer, Some(Key::Enter));
+        assert_eq!(
+            layer.take_response("name"),
+            Some(ModalResponse::Text(String::from("Jan")))
+        );
+        assert!(layer.is_open_with("pick"));
+        // egui needs a frame to notice the modal below is on top now
+        frame(&ctx, &mut layer, None);
+        frame(&ctx, &mut layer, Some(Key::Escape));
+        assert_eq!(layer.take_response("pick"), Some(ModalResponse::Cancelled));
+    }
+
+    #[test]
+    fn reopening_or_leaving_the_screen_drops_responses() {
+        let ctx = Context::default();
+        let mut layer = ModalLayer::new();
+        layer.open("quit", Modal::confirm("Quit?", ""));
+        frame(&ctx, &mut layer, None);
+        frame(&ctx, &mut layer, Some(Key::Enter));
+        layer.open("quit", Modal::confirm("Really?", ""));
+        assert_eq!(layer.take_response("quit"), None);
+        frame(&ctx, &mut layer, None);
+        frame(&ctx, &mut layer, Some(Key::Enter));
+        layer.open("other", Modal::confirm("Other", ""));
+        layer.screen_changed();
+        assert!(!layer.is_open());
+        assert_eq!(layer.take_response("quit"), None);
+    }
+}
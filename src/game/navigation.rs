@@ -0,0 +1,459 @@
+use crate::game::registry::ScreenRegistry;
+use crate::sprintln;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Addresses a screen inside the [`ScreenRegistry`].
+///
+/// The built-in screens get their own variant, so a misspelled route is a compile
+/// error instead of a silent fallback. Screens you bring yourself go into [`Route::Custom`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Route {
+    MainMenu,
+    GameSetup,
+    Game,
+    Settings,
+    Custom(String),
+}
+impl Route {
+    pub fn name(&self) -> &str {
+        match self {
+            Route::MainMenu => "main",
+            Route::GameSetup => "game_setup",
+            Route::Game => "game",
+            Route::Settings => "settings",
+            Route::Custom(name) => name,
+        }
+    }
+}
+impl From<&str> for Route {
+    fn from(name: &str) -> Self {
+        match name {
+            "main" => Route::MainMenu,
+            "game_setup" => Route::GameSetup,
+            "game" => Route::Game,
+            "settings" => Route::Settings,
+            _ => Route::Custom(name.to_string()),
+        }
+    }
+}
+impl Display for Route {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub type RouteParams = BTreeMap<String, String>;
+
+/// A [`Route`] together with the parameters it was opened with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub route: Route,
+    pub params: RouteParams,
+}
+impl Location {
+    pub fn new(route: Route) -> Self {
+        Self {
+            route,
+            params: RouteParams::new(),
+        }
+    }
+    pub fn with_params(route: Route, params: RouteParams) -> Self {
+        Self { route, params }
+    }
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params.get(key).map(String::as_str)
+    }
+}
+impl From<Route> for Location {
+    fn from(route: Route) -> Self {
+        Self::new(route)
+    }
+}
+/// Parses the format written by [`Display`], e.g. `game_setup?players=3`.
+///
+/// Route, keys and values are percent-encoded, so they may contain `?`, `&` and `=`.
+impl FromStr for Location {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (route, query) = s.split_once('?').unwrap_or((s, ""));
+        let params = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key), decode(value))
+            })
+            .collect();
+        Ok(Self::with_params(
+            Route::from(decode(route).as_str()),
+            params,
+        ))
+    }
+}
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", encode(self.route.name()))?;
+        for (idx, (key, value)) in self.params.iter().enumerate() {
+            let sep = if idx == 0 { '?' } else { '&' };
+            write!(f, "{sep}{}={}", encode(key), encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes like JavaScript's `encodeURIComponent`.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+fn encode(component: &str) -> String {
+    utf8_percent_encode(component, COMPONENT).to_string()
+}
+/// Keeps malformed escapes as they are instead of dropping the whole location.
+fn decode(component: &str) -> String {
+    percent_decode_str(component)
+        .decode_utf8()
+        .map_or_else(|_| component.to_string(), String::from)
+}
+
+#[derive(Debug, Clone)]
+enum Request {
+    Push(Location),
+    Replace(Location),
+    Back,
+    Forward,
+    PopTo(Route),
+}
+
+/// Navigation stack with back/forward history.
+///
+/// Screens only *request* a navigation. The [`App`](crate::game::App) applies it once
+/// the current screen is done with its frame and drops requests for routes that
+/// aren't registered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Navigator {
+    back: Vec<Location>,
+    current: Location,
+    forward: Vec<Location>,
+    #[serde(skip)]
+    pending: Option<Request>,
+}
+impl Navigator {
+    pub fn new(start: Route) -> Self {
+        Self {
+            back: vec![],
+            current: start.into(),
+            forward: vec![],
+            pending: None,
+        }
+    }
+}
+/// Requests
+impl Navigator {
+    /// Opens `route` on top of the current screen.
+    pub fn push(&mut self, route: Route) {
+        self.pending = Some(Request::Push(route.into()));
+    }
+    pub fn push_with(&mut self, route: Route, params: RouteParams) {
+        self.push_location(Location::with_params(route, params));
+    }
+    pub fn push_location(&mut self, location: Location) {
+        self.pending = Some(Request::Push(location));
+    }
+    /// Swaps the current screen for `route` without touching the history.
+    pub fn replace(&mut self, route: Route) {
+        self.pending = Some(Request::Replace(route.into()));
+    }
+    pub fn replace_with(&mut self, route: Route, params: RouteParams) {
+        self.pending = Some(Request::Replace(Location::with_params(route, params)));
+    }
+    /// Returns to the previous screen. Does nothing if there is none.
+    pub fn back(&mut self) {
+        self.pending = Some(Request::Back);
+    }
+    pub fn forward(&mut self) {
+        self.pending = Some(Request::Forward);
+    }
+    /// Goes back until `route` is the current screen, or pushes it if it isn't in the history.
+    pub fn pop_to(&mut self, route: Route) {
+        self.pending = Some(Request::PopTo(route));
+    }
+}
+/// Getter
+impl Navigator {
+    pub fn current(&self) -> &Location {
+        &self.current
+    }
+    pub fn route(&self) -> &Route {
+        &self.current.route
+    }
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.current.param(key)
+    }
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+    pub fn history(&self) -> &[Location] {
+        &self.back
+    }
+    /// The location [`Navigator::back`] would return to.
+    pub fn peek_back(&self) -> Option<&Location> {
+        self.back.last()
+    }
+    /// The location [`Navigator::forward`] would return to.
+    pub fn peek_forward(&self) -> Option<&Location> {
+        self.forward.last()
+    }
+}
+/// Internal
+impl Navigator {
+    /// Applies the pending request and returns the resulting [`Transition`], if the
+    /// current screen changed.
+    pub(crate) fn apply(&mut self, screens: &ScreenRegistry) -> Option<Transition> {
+        let request = self.pending.take()?;
+        let from = self.current.clone();
+        let (kind, dropped) = match request {
+            Request::Push(location) | Request::Replace(location)
+                if !screens.contains(&location.route) =>
+            {
+                sprintln!("No screen registered for route \"{}\"", location.route);
+                return None;
+            }
+            Request::Push(location) => {
+                let previous = std::mem::replace(&mut self.current, location);
+                self.back.push(previous);
+                self.forward.clear();
+                (TransitionKind::Push, vec![])
+            }
+            Request::Replace(location) => {
+                self.current = location;
+                (TransitionKind::Replace, vec![])
+            }
+            Request::Back => {
+                self.back
+                    .retain(|location| screens.contains(&location.route));
+                let location = self.back.pop()?;
+                let previous = std::mem::replace(&mut self.current, location);
+                self.forward.push(previous);
+                (TransitionKind::Back, vec![])
+            }
+            Request::Forward => {
+                self.forward
+                    .retain(|location| screens.contains(&location.route));
+                let location = self.forward.pop()?;
+                let previous = std::mem::replace(&mut self.current, location);
+                self.back.push(previous);
+                (TransitionKind::Forward, vec![])
+            }
+            Request::PopTo(route) => {
+                if self.current.route == route {
+                    return None;
+                }
+                match self
+                    .back
+                    .iter()
+                    .rposition(|location| location.route == route)
+                {
+                    Some(idx) => {
+                        let mut dropped = self.back.split_off(idx);
+                        self.current = dropped.remove(0);
+                        self.forward.clear();
+                        (TransitionKind::Back, dropped)
+                    }
+                    None => {
+                        self.pending = Some(Request::Push(route.into()));
+                        return self.apply(screens);
+                    }
+                }
+            }
+        };
+        Some(Transition {
+            from,
+            to: self.current.clone(),
+            kind,
+            dropped,
+        })
+    }
+}
+/// What kind of navigation lead to a [`Transition`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// `to` got opened on top of `from`, which stays in the history.
+    Push,
+    /// `to` took the place of `from`, which is gone.
+    Replace,
+    /// `from` got closed and `to` is shown again.
+    Back,
+    /// `to` got reopened from the forward history.
+    Forward,
+}
+
+/// A change of the current screen.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub from: Location,
+    pub to: Location,
+    pub kind: TransitionKind,
+    /// Suspended screens that got removed from the history along the way.
+    pub dropped: Vec<Location>,
+}
+impl Default for Navigator {
+    fn default() -> Self {
+        Self::new(Route::MainMenu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::screen::MainMenu;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn screens(routes: &[Route]) -> ScreenRegistry {
+        let mut screens = ScreenRegistry::new();
+        for route in routes {
+            screens.replace(route.clone(), Rc::new(RefCell::new(MainMenu::new())));
+        }
+        screens
+    }
+    fn routes(locations: &[Location]) -> Vec<Route> {
+        locations
+            .iter()
+            .map(|location| location.route.clone())
+            .collect()
+    }
+    fn custom(name: &str) -> Route {
+        Route::Custom(name.to_string())
+    }
+
+    #[test]
+    fn back_and_forward_stop_at_the_ends() {
+        let screens = screens(&[Route::MainMenu, Route::GameSetup, Route::Game]);
+        let mut nav = Navigator::new(Route::MainMenu);
+        nav.back();
+        assert!(nav.apply(&screens).is_none());
+        nav.forward();
+        assert!(nav.apply(&screens).is_none());
+        nav.push(Route::GameSetup);
+        nav.apply(&screens).unwrap();
+        nav.push(Route::Game);
+        nav.apply(&screens).unwrap();
+        assert_eq!(routes(nav.history()), [Route::MainMenu, Route::GameSetup]);
+
+        nav.back();
+        let transition = nav.apply(&screens).unwrap();
+        assert_eq!(transition.kind, TransitionKind::Back);
+        assert_eq!(
+            (transition.from.route, transition.to.route),
+            (Route::Game, Route::GameSetup)
+        );
+        nav.back();
+        nav.apply(&screens).unwrap();
+        assert_eq!(nav.route(), &Route::MainMenu);
+        assert!(!nav.can_go_back());
+        nav.back();
+        assert!(nav.apply(&screens).is_none());
+        assert_eq!(nav.route(), &Route::MainMenu);
+
+        nav.forward();
+        assert_eq!(nav.apply(&screens).unwrap().kind, TransitionKind::Forward);
+        nav.forward();
+        nav.apply(&screens).unwrap();
+        assert_eq!(nav.route(), &Route::Game);
+        assert!(!nav.can_go_forward());
+        nav.forward();
+        assert!(nav.apply(&screens).is_none());
+        assert_eq!(routes(nav.history()), [Route::MainMenu, Route::GameSetup]);
+    }
+
+    #[test]
+    fn push_drops_the_forward_history() {
+        let screens = screens(&[Route::MainMenu, Route::GameSetup, Route::Settings]);
+        let mut nav = Navigator::new(Route::MainMenu);
+        nav.push(Route::GameSetup);
+        nav.apply(&screens);
+        nav.back();
+        nav.apply(&screens);
+        assert_eq!(nav.peek_forward().unwrap().route, Route::GameSetup);
+        nav.push(Route::Settings);
+        assert_eq!(nav.apply(&screens).unwrap().kind, TransitionKind::Push);
+        assert!(!nav.can_go_forward());
+        assert_eq!(routes(nav.history()), [Route::MainMenu]);
+        // Replacing keeps the history as it is
+        nav.replace(Route::GameSetup);
+        assert_eq!(nav.apply(&screens).unwrap().kind, TransitionKind::Replace);
+        assert_eq!(routes(nav.history()), [Route::MainMenu]);
+        // Unregistered routes are dropped
+        nav.push(custom("nowhere"));
+        assert!(nav.apply(&screens).is_none());
+        assert_eq!(nav.route(), &Route::GameSetup);
+    }
+
+    #[test]
+    fn pop_to_goes_back_or_pushes() {
+        let screens = screens(&[Route::MainMenu, custom("a"), custom("b"), custom("c")]);
+        let mut nav = Navigator::new(Route::MainMenu);
+        for name in ["a", "b", "c"] {
+            nav.push(custom(name));
+            nav.apply(&screens);
+        }
+        nav.pop_to(custom("c"));
+        assert!(nav.apply(&screens).is_none());
+
+        nav.pop_to(custom("a"));
+        let transition = nav.apply(&screens).unwrap();
+        assert_eq!(transition.kind, TransitionKind::Back);
+        assert_eq!(transition.to.route, custom("a"));
+        assert_eq!(routes(&transition.dropped), [custom("b")]);
+        assert_eq!(routes(nav.history()), [Route::MainMenu]);
+        assert!(!nav.can_go_forward());
+
+        // Not in the history, so it's pushed instead
+        nav.pop_to(custom("c"));
+        let transition = nav.apply(&screens).unwrap();
+        assert_eq!(transition.kind, TransitionKind::Push);
+        assert_eq!(routes(nav.history()), [Route::MainMenu, custom("a")]);
+        // And dropped if it's not registered either
+        nav.pop_to(Route::Settings);
+        assert!(nav.apply(&screens).is_none());
+        assert_eq!(nav.route(), &custom("c"));
+    }
+
+    #[test]
+    fn locations_round_trip_with_escapes() {
+        let mut params = RouteParams::new();
+        params.insert("a&b".to_string(), "x=y?z".to_string());
+        params.insert("deck".to_string(), "Mein Stapel #2".to_string());
+        let location = Location::with_params(Route::Custom("my route/ü".to_string()), params);
+        let text = location.to_string();
+        assert!(!text.contains(' ') && !text.contains('#'));
+        assert_eq!(text.matches('?').count(), 1);
+        assert_eq!(text.parse::<Location>().unwrap(), location);
+        assert_eq!(
+            "game_setup?players=3".parse::<Location>().unwrap(),
+            Location::with_params(
+                Route::GameSetup,
+                [("players".to_string(), "3".to_string())].into()
+            )
+        );
+        let malformed = "game?x=%zz&y=%FF".parse::<Location>().unwrap();
+        assert_eq!(malformed.param("x"), Some("%zz"));
+        assert_eq!(malformed.param("y"), Some("%FF"));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::game::navigation::Route;
+use crate::game::screen::ScreenWidget;
+use std::cell::RefCell;
+use std::collections::{hash_map, HashMap};
+use std::rc::Rc;
+
+pub type ScreenRef = Rc<RefCell<dyn ScreenWidget>>;
+
+/// All screens the [`App`](crate::game::App) knows about, addressed by their [`Route`].
+///
+/// The registry gets handed to every [`ScreenWidget::update`] call, so screens can
+/// register screens they build on the fly (e.g. a freshly configured game) and
+/// drop them again once they aren't needed anymore.
+#[derive(Default)]
+pub struct ScreenRegistry {
+    screens: HashMap<Route, ScreenRef>,
+}
+impl ScreenRegistry {
+    pub fn new() -> Self {
+        Self {
+            screens: HashMap::new(),
+        }
+    }
+    /// Registers `screen` under `route` if that route isn't taken yet.
+    #[allow(clippy::result_unit_err)]
+    pub fn register(&mut self, route: Route, screen: ScreenRef) -> Result<(), ()> {
+        if let hash_map::Entry::Vacant(e) = self.screens.entry(route) {
+            e.insert(screen);
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+    /// Registers `screen` under `route` and returns the screen it replaced, if any.
+    pub fn replace(&mut self, route: Route, screen: ScreenRef) -> Option<ScreenRef> {
+        self.screens.insert(route, screen)
+    }
+    /// Removes the screen registered under `route`.
+    ///
+    /// It's fine for a screen to unregister itself during its own update, it stays
+    /// alive until the current frame is done with it.
+    pub fn unregister(&mut self, route: &Route) -> Option<ScreenRef> {
+        self.screens.remove(route)
+    }
+    pub fn get(&self, route: &Route) -> Option<ScreenRef> {
+        self.screens.get(route).map(Rc::clone)
+    }
+    pub fn contains(&self, route: &Route) -> bool {
+        self.screens.contains_key(route)
+    }
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.screens.keys()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Route, &ScreenRef)> {
+        self.screens.iter()
+    }
+}
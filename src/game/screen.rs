@@ -1,16 +1,60 @@
-use crate::game::card::{CardConfig, CardEncoding, DirectoryCardType, SimpleCard};
-use crate::game::field::{FieldWidget, SimpleField, SimpleFieldKind::Stack};
+use crate::game::card::embedded::default_deck;
+use crate::game::card::{CardConfig, CardEncoding, CardId, DirectoryCardType, SimpleCard};
+use crate::game::field::{
+    CardAction, FieldWidget, SimpleField, SimpleFieldKind::Stack, SimpleFieldState,
+};
+use crate::game::modal::{Modal, ModalLayer, ModalResponse};
+use crate::game::navigation::{Navigator, Route};
+use crate::game::registry::ScreenRegistry;
+use crate::game::settings::Settings;
+use crate::game::shortcuts::{Binding, ShortcutAction, ShortcutRegistry};
+use crate::game::toasts::Toasts;
 use crate::sprintln;
 use eframe::Frame;
-use egui::{vec2, Align, Context, Layout, Rect, UiBuilder};
+use egui::{vec2, Align, Context, Key, Layout, Modifiers, Rect, ThemePreference, UiBuilder};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::rc::Rc;
 
 pub trait ScreenWidget {
-    fn update(&mut self, next_screen: Rc<RefCell<String>>, ctx: &Context, frame: &mut Frame);
+    fn update(
+        &mut self,
+        navigator: &mut Navigator,
+        screens: &mut ScreenRegistry,
+        shortcuts: &mut ShortcutRegistry,
+        modals: &mut ModalLayer,
+        ctx: &Context,
+        frame: &mut Frame,
+    );
+    /// Declares the keyboard shortcuts of this screen, called every frame before
+    /// [`ScreenWidget::update`] while the screen is the current one.
+    fn declare_shortcuts(&self, _shortcuts: &mut ShortcutRegistry) {}
+    /// Called when the screen becomes the current screen through a push or replace.
+    fn on_enter(&mut self, _ctx: &Context) {}
+    /// Called when the screen gets closed and removed from the history.
+    fn on_exit(&mut self, _ctx: &Context) {}
+    /// Called when another screen gets pushed on top of this one.
+    fn on_suspend(&mut self, _ctx: &Context) {}
+    /// Called when the screen becomes the current screen again after being suspended.
+    fn on_resume(&mut self, _ctx: &Context) {}
+    /// Serialized state the [`App`](crate::game::App) persists across reloads,
+    /// `None` if there is nothing worth keeping.
+    fn save_state(&self) -> Option<String> {
+        None
+    }
+    /// Restores a state previously returned by [`ScreenWidget::save_state`].
+    fn restore_state(&mut self, _state: &str) {}
 }
 impl ScreenWidget for MainMenu {
-    fn update(&mut self, next_screen: Rc<RefCell<String>>, ctx: &Context, _frame: &mut Frame) {
+    fn update(
+        &mut self,
+        navigator: &mut Navigator,
+        _screens: &mut ScreenRegistry,
+        shortcuts: &mut ShortcutRegistry,
+        _modals: &mut ModalLayer,
+        ctx: &Context,
+        _frame: &mut Frame,
+    ) {
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut rect = ui.max_rect();
             let width = rect.width() / 3.0;
@@ -23,27 +67,140 @@ impl ScreenWidget for MainMenu {
                 |ui| {
                     ui.vertical_centered_justified(|ui| {
                         ui.add_space(20.0);
-                        if ui.button("Start").clicked() {
+                        if ui.button("Start").clicked() || shortcuts.take(&ShortcutAction::Confirm)
+                        {
                             sprintln!("setup started");
-                            *next_screen.borrow_mut() = String::from("game_setup");
+                            navigator.push(Route::GameSetup);
                         };
                         ui.add_space(5.0);
                         if ui.button("Settings").clicked() {
                             sprintln!("settings opened");
-                            *next_screen.borrow_mut() = String::from("settings");
+                            navigator.push(Route::Settings);
                         };
                         ui.add_space(5.0);
                         if ui.button("Print Screen").clicked() {
-                            sprintln!("{}", next_screen.borrow());
+                            sprintln!("{}", navigator.current());
                         };
                     });
                 },
             );
         });
     }
+    fn declare_shortcuts(&self, shortcuts: &mut ShortcutRegistry) {
+        let _ = shortcuts.declare(Binding::new(
+            Modifiers::NONE,
+            Key::Enter,
+            ShortcutAction::Confirm,
+        ));
+    }
+}
+impl ScreenWidget for SettingsScreen {
+    fn update(
+        &mut self,
+        navigator: &mut Navigator,
+        _screens: &mut ScreenRegistry,
+        _shortcuts: &mut ShortcutRegistry,
+        _modals: &mut ModalLayer,
+        ctx: &Context,
+        _frame: &mut Frame,
+    ) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let mut rect = ui.max_rect();
+            let width = rect.width() / 3.0;
+            rect.set_left(width);
+            rect.set_right(2.0 * width);
+            ui.allocate_new_ui(
+                UiBuilder::new()
+                    .layout(Layout::top_down_justified(Align::Min))
+                    .max_rect(rect),
+                |ui| {
+                    ui.vertical_centered_justified(|ui| {
+                        let mut settings = self.settings.borrow_mut();
+                        let previous = settings.clone();
+                        ui.add_space(20.0);
+                        ui.label("Text Sizes");
+                        for (style, size) in settings.text_sizes.iter_mut() {
+                            ui.horizontal(|ui| {
+                                ui.label(style.to_string());
+                                ui.add(egui::Slider::new(size, 6.0..=64.0));
+                            });
+                        }
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Theme");
+                            ui.selectable_value(
+                                &mut settings.theme,
+                                ThemePreference::Light,
+                                "Light",
+                            );
+                            ui.selectable_value(&mut settings.theme, ThemePreference::Dark, "Dark");
+                            ui.selectable_value(
+                                &mut settings.theme,
+                                ThemePreference::System,
+                                "System",
+                            );
+                        });
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Card Size");
+                            let card_size = &mut settings.card_size;
+                            ui.add(egui::DragValue::new(&mut card_size.x).range(10.0..=1000.0));
+                            ui.label("x");
+                            ui.add(egui::DragValue::new(&mut card_size.y).range(10.0..=1000.0));
+                        });
+                        ui.add_space(5.0);
+                        if ui.button("Reset").clicked() {
+                            *settings = Settings::default();
+                            settings.complete_text_sizes(ctx);
+                        }
+                        ui.add_space(5.0);
+                        if ui.button("Back").clicked() {
+                            navigator.back();
+                        }
+                        if *settings != previous {
+                            settings.apply(ctx);
+                        }
+                    });
+                },
+            );
+        });
+    }
+    fn on_enter(&mut self, ctx: &Context) {
+        self.settings.borrow_mut().complete_text_sizes(ctx);
+    }
+}
+impl GameSetupScreen {
+    /// Loads the typed in directory if there is one pending, otherwise asks for one.
+    fn load_directory(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = self.pending_path.take() {
+            DirectoryCardType::new_from_path(
+                Rc::clone(&self.directory),
+                self.toasts.clone(),
+                &path,
+            );
+            return;
+        }
+        DirectoryCardType::new_from_selection(Rc::clone(&self.directory), self.toasts.clone());
+    }
+    /// Selects the [`default_deck`] compiled into the binary.
+    fn use_default_deck(&self) {
+        let card_type = default_deck().card_type();
+        self.toasts
+            .info(format!("Loaded {} cards of the default deck", card_type.T));
+        self.directory.borrow_mut().replace(card_type);
+    }
 }
 impl ScreenWidget for GameSetupScreen {
-    fn update(&mut self, next_screen: Rc<RefCell<String>>, ctx: &Context, _frame: &mut Frame) {
+    fn update(
+        &mut self,
+        navigator: &mut Navigator,
+        screens: &mut ScreenRegistry,
+        shortcuts: &mut ShortcutRegistry,
+        modals: &mut ModalLayer,
+        ctx: &Context,
+        _frame: &mut Frame,
+    ) {
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut rect = ui.max_rect();
             let width = rect.width() / 3.0;
@@ -63,19 +220,112 @@ impl ScreenWidget for GameSetupScreen {
                                 Some(dir) => ui.label(&dir.path),
                             }
                         });
-                        #[cfg(target_arch = "wasm32")]
                         ui.add_space(5.0);
-                        if ui.button("Select Directory").clicked() {
-                            #[cfg(target_arch = "wasm32")]
-                            DirectoryCardType::new_from_selection(Rc::clone(&self.directory));
+                        let (select, default_deck) = ui.columns(2, |columns| {
+                            (
+                                columns[0].button("Select Directory").clicked(),
+                                columns[1].button("Default Deck").clicked(),
+                            )
+                        });
+                        if select {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            {
+                                self.pending_path = None;
+                            }
+                            if self.directory.borrow().is_some() {
+                                modals.open(
+                                    "overwrite_directory",
+                                    Modal::confirm(
+                                        "Replace Directory?",
+                                        "The currently selected directory will be replaced.",
+                                    )
+                                    .confirm_label("Select Directory"),
+                                );
+                            } else {
+                                self.load_directory();
+                            }
+                        }
+                        match modals.take_response("overwrite_directory") {
+                            Some(ModalResponse::Confirmed) => self.load_directory(),
+                            #[cfg(not(target_arch = "wasm32"))]
+                            Some(_) => self.pending_path = None,
+                            _ => {}
+                        }
+                        if default_deck {
+                            if self.directory.borrow().is_some() {
+                                modals.open(
+                                    "overwrite_with_default",
+                                    Modal::confirm(
+                                        "Replace Directory?",
+                                        "The currently selected directory will be replaced by \
+                                        the default deck.",
+                                    )
+                                    .confirm_label("Use Default Deck"),
+                                );
+                            } else {
+                                self.use_default_deck();
+                            }
+                        }
+                        if modals.take_response("overwrite_with_default")
+                            == Some(ModalResponse::Confirmed)
+                        {
+                            self.use_default_deck();
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            let load = ui.horizontal(|ui| {
+                                let edit = egui::TextEdit::singleline(&mut self.path_input)
+                                    .hint_text("or enter a path");
+                                let enter = ui.add(edit).lost_focus()
+                                    && ui.input(|i| i.key_pressed(Key::Enter));
+                                let path = self.path_input.trim();
+                                let load = egui::Button::new("Load");
+                                (ui.add_enabled(!path.is_empty(), load).clicked() || enter)
+                                    && !path.is_empty()
+                            });
+                            if load.inner {
+                                self.pending_path = Some(self.path_input.trim().into());
+                                if self.directory.borrow().is_some() {
+                                    modals.open(
+                                        "overwrite_directory",
+                                        Modal::confirm(
+                                            "Replace Directory?",
+                                            "The currently selected directory will be replaced.",
+                                        )
+                                        .confirm_label("Load"),
+                                    );
+                                } else {
+                                    self.load_directory();
+                                }
+                            }
                         }
                         ui.add_space(5.0);
+                        if let Some(dir) = self.directory.borrow_mut().as_mut() {
+                            ui.horizontal(|ui| {
+                                ui.label("Card Back:");
+                                let mut back = dir.back_image().map(str::to_string);
+                                egui::ComboBox::from_id_salt("Card Back")
+                                    .selected_text(back.as_deref().unwrap_or("Default"))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut back, None, "Default");
+                                        let names = dir.all_images().map(String::as_str);
+                                        for name in names.chain(dir.back_image()) {
+                                            let value = Some(name.to_string());
+                                            ui.selectable_value(&mut back, value, name);
+                                        }
+                                    });
+                                if back.as_deref() != dir.back_image() {
+                                    dir.set_back(back);
+                                }
+                            });
+                            ui.add_space(5.0);
+                        }
                         ui.horizontal(|ui| {
                             ui.label("# Players");
                             let drag = egui::DragValue::new(&mut self.players);
                             ui.add(drag);
                             let dec = egui::Button::new("-").min_size(vec2(30.0, 0.0));
-                            if ui.add(dec).clicked() && self.players > 1 {
+                            if ui.add(dec).clicked() && self.players > 2 {
                                 self.players = self.players.saturating_sub(1);
                             }
                             let inc = egui::Button::new("+").min_size(vec2(30.0, 0.0));
@@ -84,27 +334,67 @@ impl ScreenWidget for GameSetupScreen {
                             }
                         });
                         ui.add_space(5.0);
-                        if ui.button("Start Game").clicked() {
-                            if let Some(game) = self.game_widget.upgrade() {
-                                let config = self.generate_config();
-                                if config.is_some() {
-                                    game.borrow_mut().game_config = config;
-                                    *next_screen.borrow_mut() = String::from("game");
+                        if ui.button("Start Game").clicked()
+                            || shortcuts.take(&ShortcutAction::Confirm)
+                        {
+                            match self.generate_config() {
+                                Ok(config) => {
+                                    let game =
+                                        Game::from_config(config).toasts(self.toasts.clone());
+                                    screens.replace(Route::Game, Rc::new(RefCell::new(game)));
+                                    navigator.push(Route::Game);
                                 }
+                                Err(reason) => self.toasts.warn(reason),
                             }
                         }
                         ui.add_space(5.0);
                         if ui.button("Back").clicked() {
-                            *next_screen.borrow_mut() = String::from("main");
+                            navigator.back();
                         }
                     });
                 },
             );
         });
     }
+    fn declare_shortcuts(&self, shortcuts: &mut ShortcutRegistry) {
+        let _ = shortcuts.declare(Binding::new(
+            Modifiers::NONE,
+            Key::Enter,
+            ShortcutAction::Confirm,
+        ));
+    }
+    /// Opening the setup freshly starts with the default player count, the selected
+    /// directory is kept.
+    fn on_enter(&mut self, _ctx: &Context) {
+        self.players = 2;
+    }
+    fn save_state(&self) -> Option<String> {
+        let state = GameSetupState {
+            players: self.players,
+            directory: self.directory.borrow().clone(),
+        };
+        ron::to_string(&state).ok()
+    }
+    fn restore_state(&mut self, state: &str) {
+        match ron::from_str::<GameSetupState<DirectoryCardType>>(state) {
+            Ok(state) => {
+                self.players = state.players;
+                *self.directory.borrow_mut() = state.directory;
+            }
+            Err(err) => sprintln!("Failed to restore game setup: {err}"),
+        }
+    }
 }
 impl ScreenWidget for Game<DirectoryCardType> {
-    fn update(&mut self, next_screen: Rc<RefCell<String>>, ctx: &Context, _frame: &mut Frame) {
+    fn update(
+        &mut self,
+        navigator: &mut Navigator,
+        screens: &mut ScreenRegistry,
+        shortcuts: &mut ShortcutRegistry,
+        modals: &mut ModalLayer,
+        ctx: &Context,
+        _frame: &mut Frame,
+    ) {
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut rect = ui.max_rect();
             let width = rect.width() / 3.0;
@@ -118,12 +408,27 @@ impl ScreenWidget for Game<DirectoryCardType> {
                     ui.add_space(20.0);
                     ui.vertical_centered_justified(|ui| {
                         if ui.button("Exit").clicked() {
-                            *next_screen.borrow_mut() = String::from("main");
+                            modals.open(
+                                "leave_game",
+                                Modal::confirm("Leave Game?", "The current game will be lost.")
+                                    .confirm_label("Leave")
+                                    .cancel_label("Stay"),
+                            );
+                        }
+                        if modals.take_response("leave_game") == Some(ModalResponse::Confirmed) {
+                            screens.unregister(&Route::Game);
+                            navigator.pop_to(Route::MainMenu);
                         }
                     });
                     if self.game_config.is_none() {
                         return;
                     }
+                    let players = self.game_config.as_ref().unwrap().players.len();
+                    for player in 0..players.min(PLAYER_KEYS.len()) {
+                        if shortcuts.take(&ShortcutAction::SelectPlayer(player)) {
+                            self.select_player(player);
+                        }
+                    }
                     ui.add_space(5.0);
                     ui.vertical_centered_justified(|ui| {
                         ui.horizontal(|ui| {
@@ -152,16 +457,18 @@ impl ScreenWidget for Game<DirectoryCardType> {
                     ui.add_space(5.0);
                     ui.label("Stack");
                     let stack = &cfg.stack;
+                    let mut actions = Vec::new();
                     if let Some(_payload) =
                         ui.add(stack.draw()).dnd_release_payload::<DNDSelector>()
                     {
                         self.drop = Some(DNDSelector::Stack)
                     }
+                    actions.extend(stack.take_action());
                     match stack.get_payload() {
                         (_, Some(_idx)) => self.drop = Some(DNDSelector::Stack),
-                        (Some(_idx), _) => {
+                        (Some(id), _) => {
                             if self.drag.is_none() {
-                                self.drag = Some(DNDSelector::Stack)
+                                self.drag = Some(DNDSelector::Card(id))
                             }
                         }
                         (None, None) => {}
@@ -172,15 +479,16 @@ impl ScreenWidget for Game<DirectoryCardType> {
                     if let Some(_payload) =
                         ui.add(field_0.draw()).dnd_release_payload::<DNDSelector>()
                     {
-                        self.drop = Some(DNDSelector::Player(self.player0_idx, field_0.cards.len()))
+                        self.drop = Some(DNDSelector::Player(self.player0_idx, field_0.len()))
                     }
+                    actions.extend(field_0.take_action());
                     match field_0.get_payload() {
                         (_, Some(idx)) => {
                             self.drop = Some(DNDSelector::Player(self.player0_idx, idx))
                         }
-                        (Some(idx), _) => {
+                        (Some(id), _) => {
                             if self.drag.is_none() {
-                                self.drag = Some(DNDSelector::Player(self.player0_idx, idx))
+                                self.drag = Some(DNDSelector::Card(id))
                             }
                         }
                         (None, None) => {}
@@ -191,19 +499,23 @@ impl ScreenWidget for Game<DirectoryCardType> {
                     if let Some(_payload) =
                         ui.add(field_1.draw()).dnd_release_payload::<DNDSelector>()
                     {
-                        self.drop = Some(DNDSelector::Player(self.player1_idx, field_1.cards.len()))
+                        self.drop = Some(DNDSelector::Player(self.player1_idx, field_1.len()))
                     }
+                    actions.extend(field_1.take_action());
                     match field_1.get_payload() {
                         (_, Some(idx)) => {
                             self.drop = Some(DNDSelector::Player(self.player1_idx, idx))
                         }
-                        (Some(idx), _) => {
+                        (Some(id), _) => {
                             if self.drag.is_none() {
-                                self.drag = Some(DNDSelector::Player(self.player1_idx, idx))
+                                self.drag = Some(DNDSelector::Card(id))
                             }
                         }
                         (None, None) => {}
                     }
+                    for action in actions {
+                        cfg.apply_action(action);
+                    }
                     if let (Some(source), Some(destination)) = (self.drag, self.drop) {
                         // sprintln!("Drag: {:?}\tDrop: {:?}", self.drag, self.drop);
                         cfg.move_card::<SimpleCard>(source, destination);
@@ -213,25 +525,21 @@ impl ScreenWidget for Game<DirectoryCardType> {
                         // sprintln!("Drag: {:?}\tDrop: {:?}", self.drag, self.drop);
                     }
                     if ui.input(|i| i.pointer.primary_down()) {
-                        if let Some(drag) = self.drag {
-                            let (img, size) = match drag {
-                                DNDSelector::Player(field_idx, card_idx) => {
-                                    let card = &cfg.players[field_idx].1.cards[card_idx];
-                                    (
-                                        cfg.players[field_idx].1.card_config.img(card),
-                                        cfg.players[field_idx].1.get_card_size(),
-                                    )
+                        if let Some(DNDSelector::Card(id)) = self.drag {
+                            // The card may have left the table mid-drag, e.g. by a reset
+                            let dragged = cfg
+                                .field_of(id)
+                                .and_then(|field| Some((field, field.get(id)?)));
+                            match dragged {
+                                Some((field, card)) => {
+                                    if let Some(pointer_pos) = ui.input(|i| i.pointer.latest_pos())
+                                    {
+                                        let size = field.footprint(card);
+                                        let rect = Rect::from_min_size(pointer_pos, size);
+                                        field.paint_card(ui, card, rect);
+                                    }
                                 }
-                                DNDSelector::Stack => {
-                                    let card = cfg.stack.cards.last().unwrap();
-                                    (cfg.stack.card_config.img(card), cfg.stack.get_card_size())
-                                }
-                                _ => {
-                                    panic!("This should not happen")
-                                }
-                            };
-                            if let Some(pointer_pos) = ui.input(|i| i.pointer.latest_pos()) {
-                                img.paint_at(ui, Rect::from_min_size(pointer_pos, size));
+                                None => self.drag = None,
                             }
                         }
                     } else if self.drag.is_some() {
@@ -241,6 +549,83 @@ impl ScreenWidget for Game<DirectoryCardType> {
             );
         });
     }
+    fn declare_shortcuts(&self, shortcuts: &mut ShortcutRegistry) {
+        let Some(cfg) = &self.game_config else {
+            return;
+        };
+        for (player, key) in PLAYER_KEYS.iter().copied().enumerate() {
+            if player < cfg.players.len() {
+                let action = ShortcutAction::SelectPlayer(player);
+                let _ = shortcuts.declare(Binding::new(Modifiers::NONE, key, action));
+            }
+        }
+    }
+    /// A drag can't survive the screen being left, the pointer gets released elsewhere.
+    fn on_suspend(&mut self, _ctx: &Context) {
+        self.drag = None;
+        self.drop = None;
+    }
+    fn on_exit(&mut self, _ctx: &Context) {
+        self.drag = None;
+        self.drop = None;
+    }
+    fn save_state(&self) -> Option<String> {
+        let Some(cfg) = self.game_config.as_ref() else {
+            return self.unreadable_save.clone();
+        };
+        let snapshot = GameSnapshot {
+            card_config: (*cfg.stack.card_config).clone(),
+            players: cfg
+                .players
+                .iter()
+                .map(|(name, field)| (name.clone(), field.state()))
+                .collect(),
+            stack: cfg.stack.state(),
+            player0_idx: self.player0_idx,
+            player1_idx: self.player1_idx,
+        };
+        ron::to_string(&snapshot).ok()
+    }
+    fn restore_state(&mut self, state: &str) {
+        match ron::from_str::<GameSnapshot<DirectoryCardType>>(state) {
+            Ok(snapshot) => {
+                let shown = [snapshot.player0_idx, snapshot.player1_idx];
+                if let Some(missing) = shown.iter().find(|&&idx| idx >= snapshot.players.len()) {
+                    self.toasts.warn(format!(
+                        "The saved game shows player {} but has only {}, it's kept until a new game starts",
+                        missing + 1,
+                        snapshot.players.len()
+                    ));
+                    self.unreadable_save = Some(state.to_string());
+                    return;
+                }
+                let card_config = Rc::new(snapshot.card_config);
+                let players = snapshot
+                    .players
+                    .into_iter()
+                    .map(|(name, field)| {
+                        (
+                            name,
+                            SimpleField::from_state(Rc::clone(&card_config), field),
+                        )
+                    })
+                    .collect();
+                let stack = SimpleField::from_state(card_config, snapshot.stack);
+                self.game_config = Some(GameState { players, stack });
+                self.player0_idx = snapshot.player0_idx;
+                self.player1_idx = snapshot.player1_idx;
+                // Ids of the replaced cards don't exist anymore
+                self.drag = None;
+                self.drop = None;
+            }
+            Err(err) => {
+                sprintln!("Failed to restore game: {err}");
+                self.toasts
+                    .warn("The saved game can't be read, it's kept until a new game starts");
+                self.unreadable_save = Some(state.to_string());
+            }
+        }
+    }
 }
 
 pub struct MainMenu {}
@@ -255,23 +640,55 @@ impl Default for MainMenu {
     }
 }
 
-pub struct GameSetupScreen<C: CardConfig = DirectoryCardType, G = Game<C>> {
+pub struct SettingsScreen {
+    settings: Rc<RefCell<Settings>>,
+}
+impl SettingsScreen {
+    pub fn new(settings: Rc<RefCell<Settings>>) -> Self {
+        Self { settings }
+    }
+}
+
+pub struct GameSetupScreen<C: CardConfig = DirectoryCardType> {
     directory: Rc<RefCell<Option<C>>>,
     players: usize,
-    pub(crate) game_widget: Weak<RefCell<G>>,
+    settings: Rc<RefCell<Settings>>,
+    toasts: Toasts,
+    /// Directory typed in by hand, for systems without a folder dialog
+    #[cfg(not(target_arch = "wasm32"))]
+    path_input: String,
+    /// The typed in directory waiting for the user to confirm replacing the selected one
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_path: Option<std::path::PathBuf>,
 }
-impl<C: CardConfig + Clone, G> GameSetupScreen<C, G> {
-    pub fn new(game_widget: Weak<RefCell<G>>) -> Self {
+impl<C: CardConfig + Clone> GameSetupScreen<C> {
+    pub fn new(settings: Rc<RefCell<Settings>>, toasts: Toasts) -> Self {
         let directory = Rc::new(RefCell::new(None));
         let players = 2;
         Self {
             directory,
             players,
-            game_widget,
+            settings,
+            toasts,
+            #[cfg(not(target_arch = "wasm32"))]
+            path_input: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_path: None,
         }
     }
-    fn generate_config(&self) -> Option<GameState<C>> {
-        let directory = Rc::new(self.directory.borrow().clone()?);
+    /// Fails with a message for the user if the setup isn't complete.
+    fn generate_config(&self) -> Result<GameState<C>, &'static str> {
+        let directory = self
+            .directory
+            .borrow()
+            .clone()
+            .ok_or("Select a card directory first")?;
+        // The game screen always shows two players
+        if self.players < 2 {
+            return Err("A game needs at least two players");
+        }
+        let directory = Rc::new(directory);
+        let card_size = self.settings.borrow().card_size;
         let mut players: Vec<(String, SimpleField<SimpleCard, C>)> = (0..self.players)
             .map(|i| {
                 (
@@ -279,22 +696,41 @@ impl<C: CardConfig + Clone, G> GameSetupScreen<C, G> {
                     SimpleField::new(Rc::clone(&directory))
                         .max_cards(4)
                         .selectable(true)
-                        .max_card_size(vec2(100.0, 150.0)),
+                        .max_card_size(card_size),
                 )
             })
             .collect();
         let mut stack = SimpleField::new(Rc::clone(&directory))
             .kind(Stack)
-            .max_card_size(vec2(100.0, 150.0));
+            .max_card_size(card_size);
         for i in 0..directory.T() {
-            let card = SimpleCard::Open(i);
-            stack.push(card);
-            players[i % self.players].1.push(SimpleCard::Open(i));
+            for _ in 0..directory.count(i) {
+                stack.push(SimpleCard::face_up(i));
+            }
+            players[i % self.players].1.push(SimpleCard::face_up(i));
         }
-        Some(GameState { players, stack })
+        Ok(GameState { players, stack })
+    }
+}
+
+impl<C: CardConfig + Clone> Default for GameSetupScreen<C> {
+    fn default() -> Self {
+        Self::new(Rc::default(), Toasts::default())
     }
 }
 
+const PLAYER_KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
 /// Struct for a game with one stack and arbitrary players
 pub struct Game<C: CardConfig> {
     pub(crate) game_config: Option<GameState<C>>,
@@ -302,6 +738,9 @@ pub struct Game<C: CardConfig> {
     player1_idx: usize,
     drag: Option<DNDSelector>,
     drop: Option<DNDSelector>,
+    /// A save that couldn't be restored, kept so it isn't overwritten until a new game starts
+    unreadable_save: Option<String>,
+    toasts: Toasts,
 }
 impl<C: CardConfig> Game<C> {
     pub fn new() -> Self {
@@ -311,8 +750,27 @@ impl<C: CardConfig> Game<C> {
             player1_idx: 1,
             drag: None,
             drop: None,
+            unreadable_save: None,
+            toasts: Toasts::new(),
+        }
+    }
+    pub fn from_config(game_config: GameState<C>) -> Self {
+        Self {
+            game_config: Some(game_config),
+            ..Self::new()
         }
     }
+    /// Where to report saves that can't be restored.
+    pub fn toasts(self, toasts: Toasts) -> Self {
+        Self { toasts, ..self }
+    }
+    /// Shows `player` in the 1. field. If it's in the 2. field already, both swap.
+    pub fn select_player(&mut self, player: usize) {
+        if self.player1_idx == player {
+            self.player1_idx = self.player0_idx;
+        }
+        self.player0_idx = player;
+    }
 }
 impl<C: CardConfig> Default for Game<C> {
     fn default() -> Self {
@@ -320,32 +778,173 @@ impl<C: CardConfig> Default for Game<C> {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct GameSetupState<C> {
+    players: usize,
+    directory: Option<C>,
+}
+
+/// A [`Game`] without the sharing between its fields, so it can be persisted.
+#[derive(Serialize, Deserialize)]
+struct GameSnapshot<C> {
+    card_config: C,
+    players: Vec<(String, SimpleFieldState<SimpleCard>)>,
+    stack: SimpleFieldState<SimpleCard>,
+    player0_idx: usize,
+    player1_idx: usize,
+}
+
 pub struct GameState<C: CardConfig> {
     players: Vec<(String, SimpleField<SimpleCard, C>)>,
     stack: SimpleField<SimpleCard, C>,
 }
 impl<C: CardConfig> GameState<C> {
+    /// Moves the card `src` refers to onto the slot `dst` refers to, keeping its [`CardId`]
+    /// and annotations.
     pub fn move_card<E: CardEncoding>(&mut self, src: DNDSelector, dst: DNDSelector) {
-        if src == dst {
+        let DNDSelector::Card(id) = src else {
             return;
-        }
-        let card = match src {
-            DNDSelector::Player(p_idx, c_idx) => self.players[p_idx].1.remove(c_idx),
-            DNDSelector::Stack => self.stack.cards.pop().unwrap(),
-            DNDSelector::Index(_) => return,
         };
-        match dst {
-            DNDSelector::Player(p_idx, c_idx) => self.players[p_idx].1.insert(c_idx, card),
-            DNDSelector::Stack => self.stack.cards.push(card),
-            #[allow(clippy::needless_return)]
-            DNDSelector::Index(_) => return,
+        let (dst_field, mut dst_idx) = match dst {
+            DNDSelector::Player(p_idx, c_idx) => (p_idx, c_idx),
+            DNDSelector::Stack => (self.players.len(), self.stack.len()),
+            DNDSelector::Card(_) => return,
+        };
+        let Some(src_field) = (0..=self.players.len()).find(|f| self.field(*f).contains(id)) else {
+            return;
         };
+        let src_idx = self.field(src_field).position(id).unwrap();
+        if src_field == dst_field {
+            // Indices behind the card shift once it's taken out
+            if dst_idx > src_idx {
+                dst_idx -= 1;
+            }
+            if dst_idx == src_idx {
+                return;
+            }
+        }
+        let annotations = self.field_mut(src_field).take_annotations(id);
+        let card = self.field_mut(src_field).remove(src_idx);
+        self.field_mut(dst_field).insert(dst_idx, card);
+        self.field_mut(dst_field).set_annotations(id, annotations);
+    }
+    /// Applies what a card's context menu asked for to the field holding it.
+    pub fn apply_action(&mut self, (id, action): (CardId, CardAction)) {
+        if let Some(field) = (0..=self.players.len()).find(|f| self.field(*f).contains(id)) {
+            self.field_mut(field).apply_action((id, action));
+        }
+    }
+    /// The field holding the card with `id`.
+    pub fn field_of(&self, id: CardId) -> Option<&SimpleField<SimpleCard, C>> {
+        (0..=self.players.len())
+            .map(|f| self.field(f))
+            .find(|field| field.contains(id))
+    }
+    /// Players first, the stack comes last.
+    fn field(&self, idx: usize) -> &SimpleField<SimpleCard, C> {
+        match self.players.get(idx) {
+            Some((_, field)) => field,
+            None => &self.stack,
+        }
+    }
+    fn field_mut(&mut self, idx: usize) -> &mut SimpleField<SimpleCard, C> {
+        if idx < self.players.len() {
+            &mut self.players[idx].1
+        } else {
+            &mut self.stack
+        }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DNDSelector {
+    /// The slot `.1` in the hand of player `.0`
     Player(usize, usize),
+    /// The top of the stack
     Stack,
-    Index(usize),
+    /// The card with this id, wherever it currently is
+    Card(CardId),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::card::Orientation;
+
+    /// Saved by the game screen before cards had an [`Orientation`], annotations and an
+    /// image origin.
+    const OLD_SAVE: &str = r#"(card_config:(path:"set",img_names:["a.png","b.png"],back_name:Some("back.png"),manifest:None,faces:[(name:"a",uri:"file://set/a.png",metadata:{},tags:[],count:1),(name:"b",uri:"file://set/b.png",metadata:{},tags:[],count:1)],back:(name:"Back",uri:"file://set/back.png",metadata:{},tags:[],count:1),T:2,w:1,natural_size:(x:2.0,y:3.0)),players:[("0",(cards:[Open(1),Masked(Some(0))],ids:[(0),(1)],kind:Horizontal,margin:4,max_cards:4,selectable:true,draggable:true,max_card_size:None)),("1",(cards:[Open(0)],ids:[(4)],kind:Horizontal,margin:4,max_cards:4,selectable:true,draggable:true,max_card_size:None))],stack:(cards:[Masked(None),Open(0)],ids:[(2),(3)],kind:Stack,margin:4,max_cards:5,selectable:true,draggable:true,max_card_size:None),player0_idx:0,player1_idx:1)"#;
+
+    #[test]
+    fn old_saves_restore() {
+        let mut game = Game::<DirectoryCardType>::new();
+        game.restore_state(OLD_SAVE);
+        let cfg = game.game_config.as_ref().expect("Old save got restored");
+        assert_eq!(
+            cfg.players[0].1.get_cards(),
+            &vec![
+                SimpleCard::face_up(1),
+                SimpleCard::Masked(Some(0), Orientation::Upright)
+            ]
+        );
+        assert_eq!(
+            cfg.stack.get_cards(),
+            &vec![
+                SimpleCard::Masked(None, Orientation::Upright),
+                SimpleCard::face_up(0)
+            ]
+        );
+        assert_eq!(cfg.stack.ids().len(), 2);
+        assert_eq!(cfg.stack.card_config.T(), 2);
+        // And the restored game saves in the current format
+        let saved = game.save_state().unwrap();
+        let mut again = Game::<DirectoryCardType>::new();
+        again.restore_state(&saved);
+        let restored = again.game_config.as_ref().unwrap();
+        assert_eq!(
+            restored.players[0].1.get_cards(),
+            cfg.players[0].1.get_cards()
+        );
+    }
+
+    #[test]
+    fn unreadable_saves_are_kept() {
+        let mut game = Game::<DirectoryCardType>::new();
+        game.restore_state("(from_the_future: true)");
+        assert!(game.game_config.is_none());
+        assert_eq!(
+            game.save_state().as_deref(),
+            Some("(from_the_future: true)")
+        );
+    }
+
+    #[test]
+    fn setups_need_two_players() {
+        let settings = Rc::new(RefCell::new(Settings::default()));
+        let mut setup = GameSetupScreen::new(settings, Toasts::new());
+        assert!(setup.generate_config().is_err());
+        *setup.directory.borrow_mut() = Some(default_deck().card_type());
+        for players in [0, 1] {
+            setup.players = players;
+            assert!(setup.generate_config().is_err());
+        }
+        setup.players = 2;
+        let cfg = setup.generate_config().unwrap();
+        assert_eq!(cfg.players.len(), 2);
+        assert_eq!(cfg.players[1].1.len(), 26);
+    }
+
+    #[test]
+    fn saves_showing_missing_players_are_kept() {
+        let one_player = OLD_SAVE.replace(
+            r#",("1",(cards:[Open(0)],ids:[(4)],kind:Horizontal,margin:4,max_cards:4,selectable:true,draggable:true,max_card_size:None))"#,
+            "",
+        );
+        let toasts = Toasts::new();
+        let mut game = Game::<DirectoryCardType>::new().toasts(toasts.clone());
+        game.restore_state(&one_player);
+        assert!(game.game_config.is_none());
+        assert_eq!(toasts.len(), 1);
+        assert_eq!(game.save_state(), Some(one_player));
+    }
 }
@@ -0,0 +1,55 @@
+use egui::{vec2, Context, TextStyle, ThemePreference, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// User preferences that survive reloads through eframe's storage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Font size per [`TextStyle`]. Styles without an entry keep egui's size.
+    pub text_sizes: BTreeMap<TextStyle, f32>,
+    pub theme: ThemePreference,
+    /// Passed to [`SimpleField::max_card_size`](crate::game::field::SimpleField::max_card_size)
+    /// for every field of a new game.
+    pub card_size: Vec2,
+}
+impl Settings {
+    pub const STORAGE_KEY: &'static str = "mcg_visual_settings";
+
+    pub fn load(storage: &dyn eframe::Storage) -> Option<Self> {
+        eframe::get_value(storage, Self::STORAGE_KEY)
+    }
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Self::STORAGE_KEY, self);
+    }
+    /// Fills in the sizes of all text styles `ctx` knows but the settings don't.
+    pub fn complete_text_sizes(&mut self, ctx: &Context) {
+        for (style, font) in ctx.style().text_styles.iter() {
+            self.text_sizes.entry(style.clone()).or_insert(font.size);
+        }
+    }
+    pub fn apply(&self, ctx: &Context) {
+        ctx.set_theme(self.theme);
+        let text_sizes = self.text_sizes.clone();
+        ctx.all_styles_mut(move |style| {
+            for (text_style, font) in style.text_styles.iter_mut() {
+                if let Some(size) = text_sizes.get(text_style) {
+                    font.size = *size;
+                }
+            }
+        });
+    }
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            text_sizes: egui::Style::default()
+                .text_styles
+                .into_iter()
+                .map(|(style, font)| (style, font.size))
+                .collect(),
+            theme: ThemePreference::System,
+            card_size: vec2(100.0, 150.0),
+        }
+    }
+}
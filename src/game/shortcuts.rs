@@ -0,0 +1,291 @@
+use crate::sprintln;
+use egui::{Context, Key, KeyboardShortcut, Modifiers};
+use std::fmt::{Display, Formatter};
+
+/// What a [`Binding`] asks for when its keys get pressed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ShortcutAction {
+    /// Return to the previous screen
+    Back,
+    /// Trigger the primary button of the current screen
+    Confirm,
+    /// Display the player with this index
+    SelectPlayer(usize),
+    /// Show or hide the list of active shortcuts
+    ToggleHelp,
+    Custom(String),
+}
+impl Display for ShortcutAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShortcutAction::Back => write!(f, "Back"),
+            ShortcutAction::Confirm => write!(f, "Confirm"),
+            // Counted from 1 like the number keys it's bound to
+            ShortcutAction::SelectPlayer(player) => write!(f, "Show player {}", player + 1),
+            ShortcutAction::ToggleHelp => write!(f, "Toggle this help"),
+            ShortcutAction::Custom(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub shortcut: KeyboardShortcut,
+    pub action: ShortcutAction,
+}
+impl Binding {
+    pub fn new(modifiers: Modifiers, key: Key, action: ShortcutAction) -> Self {
+        Self {
+            shortcut: KeyboardShortcut::new(modifiers, key),
+            action,
+        }
+    }
+}
+
+/// Two bindings for the same keys. The first one declared stays active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub active: Binding,
+    pub ignored: Binding,
+}
+
+/// Keyboard shortcuts of the [`App`](crate::game::App) and the current screen.
+///
+/// Screens declare theirs every frame through
+/// [`ScreenWidget::declare_shortcuts`](crate::game::screen::ScreenWidget::declare_shortcuts)
+/// and [`ShortcutRegistry::take`] the actions that got triggered during their update.
+/// Whatever they don't take is handled by the app, e.g. [`ShortcutAction::Back`].
+pub struct ShortcutRegistry {
+    global: Vec<Binding>,
+    screen: Vec<Binding>,
+    conflicts: Vec<Conflict>,
+    triggered: Vec<ShortcutAction>,
+    show_help: bool,
+}
+impl ShortcutRegistry {
+    pub fn new() -> Self {
+        Self {
+            global: vec![
+                Binding::new(Modifiers::NONE, Key::Escape, ShortcutAction::Back),
+                Binding::new(Modifiers::NONE, Key::F1, ShortcutAction::ToggleHelp),
+            ],
+            screen: vec![],
+            conflicts: vec![],
+            triggered: vec![],
+            show_help: false,
+        }
+    }
+    /// Adds a binding for the current screen. Fails if the keys are bound already.
+    pub fn declare(&mut self, binding: Binding) -> Result<(), Conflict> {
+        let active = self
+            .bindings()
+            .find(|active| active.shortcut == binding.shortcut)
+            .cloned();
+        match active {
+            Some(active) => {
+                let conflict = Conflict {
+                    active,
+                    ignored: binding,
+                };
+                if !self.conflicts.contains(&conflict) {
+                    sprintln!(
+                        "Shortcut conflict: {:?} is bound to \"{}\" and \"{}\"",
+                        conflict.active.shortcut,
+                        conflict.active.action,
+                        conflict.ignored.action
+                    );
+                    self.conflicts.push(conflict.clone());
+                }
+                Err(conflict)
+            }
+            None => {
+                self.screen.push(binding);
+                Ok(())
+            }
+        }
+    }
+    /// Whether `action` got triggered this frame, without consuming it.
+    pub fn triggered(&self, action: &ShortcutAction) -> bool {
+        self.triggered.contains(action)
+    }
+    /// Consumes `action` if it got triggered this frame.
+    pub fn take(&mut self, action: &ShortcutAction) -> bool {
+        match self.triggered.iter().position(|a| a == action) {
+            Some(idx) => {
+                self.triggered.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn bindings(&self) -> impl Iterator<Item = &Binding> {
+        self.global.iter().chain(self.screen.iter())
+    }
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+}
+/// Internal
+impl ShortcutRegistry {
+    pub(crate) fn begin_frame(&mut self) {
+        self.screen.clear();
+        self.triggered.clear();
+    }
+    /// Collects the actions of all pressed bindings. Call after the screen declared its own.
+    pub(crate) fn consume(&mut self, ctx: &Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        // Shortcuts match logically, so e.g. `1` would also eat `Ctrl+1` if checked first
+        let mut bindings: Vec<&Binding> = self.bindings().collect();
+        bindings
+            .sort_by_key(|binding| std::cmp::Reverse(modifier_count(binding.shortcut.modifiers)));
+        let pressed: Vec<ShortcutAction> = ctx.input_mut(|input| {
+            bindings
+                .into_iter()
+                .filter(|binding| input.consume_shortcut(&binding.shortcut))
+                .map(|binding| binding.action.clone())
+                .collect()
+        });
+        self.triggered.extend(pressed);
+        if self.take(&ShortcutAction::ToggleHelp) {
+            self.show_help = !self.show_help;
+        }
+    }
+    /// Clears conflicts of the previous screen, they get detected again when redeclared.
+    pub(crate) fn screen_changed(&mut self) {
+        self.conflicts.clear();
+    }
+    pub(crate) fn show_help(&mut self, ctx: &Context) {
+        let mut open = self.show_help;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcut_help")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for binding in self.bindings() {
+                            ui.monospace(ctx.format_shortcut(&binding.shortcut));
+                            ui.label(binding.action.to_string());
+                            ui.end_row();
+                        }
+                    });
+                for conflict in &self.conflicts {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!(
+                            "{} is already bound, \"{}\" is ignored",
+                            ctx.format_shortcut(&conflict.ignored.shortcut),
+                            conflict.ignored.action
+                        ),
+                    );
+                }
+            });
+        self.show_help = open;
+    }
+}
+fn modifier_count(modifiers: Modifiers) -> usize {
+    [
+        modifiers.alt,
+        modifiers.ctrl,
+        modifiers.shift,
+        modifiers.mac_cmd,
+        modifiers.command,
+    ]
+    .iter()
+    .filter(|pressed| **pressed)
+    .count()
+}
+impl Default for ShortcutRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, RawInput};
+
+    fn custom(name: &str) -> ShortcutAction {
+        ShortcutAction::Custom(name.to_string())
+    }
+    /// Runs one frame in which `keys` get pressed, `declare` being the screen's bindings.
+    fn press(
+        registry: &mut ShortcutRegistry,
+        keys: &[(Modifiers, Key)],
+        declare: impl Fn(&mut ShortcutRegistry),
+    ) {
+        let events = keys
+            .iter()
+            .map(|&(modifiers, key)| Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            })
+            .collect();
+        let input = RawInput {
+            events,
+            ..Default::default()
+        };
+        let _ = Context::default().run(input, |ctx| {
+            registry.begin_frame();
+            declare(registry);
+            registry.consume(ctx);
+        });
+    }
+
+    #[test]
+    fn conflicting_declarations_are_ignored_and_reported_once() {
+        let mut registry = ShortcutRegistry::new();
+        let escape = Binding::new(Modifiers::NONE, Key::Escape, custom("close"));
+        let conflict = registry.declare(escape.clone()).unwrap_err();
+        assert_eq!(conflict.active.action, ShortcutAction::Back);
+        assert_eq!(conflict.ignored, escape);
+
+        let first = Binding::new(Modifiers::NONE, Key::A, custom("first"));
+        let second = Binding::new(Modifiers::NONE, Key::A, custom("second"));
+        assert!(registry.declare(first.clone()).is_ok());
+        assert_eq!(registry.declare(second.clone()).unwrap_err().active, first);
+        // Different modifiers are different keys
+        assert!(registry
+            .declare(Binding::new(Modifiers::CTRL, Key::A, custom("ctrl")))
+            .is_ok());
+        assert_eq!(registry.conflicts().len(), 2);
+
+        // Redeclaring every frame doesn't pile up the same conflicts
+        registry.begin_frame();
+        assert!(registry.declare(first).is_ok());
+        assert!(registry.declare(second).is_err());
+        assert!(registry.declare(escape).is_err());
+        assert_eq!(registry.conflicts().len(), 2);
+        assert_eq!(registry.bindings().count(), 3);
+
+        registry.screen_changed();
+        assert!(registry.conflicts().is_empty());
+    }
+
+    #[test]
+    fn pressed_bindings_trigger_once() {
+        let mut registry = ShortcutRegistry::new();
+        let declare = |registry: &mut ShortcutRegistry| {
+            let _ = registry.declare(Binding::new(Modifiers::NONE, Key::Num1, custom("one")));
+            let _ = registry.declare(Binding::new(Modifiers::CTRL, Key::Num1, custom("ctrl one")));
+        };
+        press(&mut registry, &[(Modifiers::CTRL, Key::Num1)], declare);
+        assert!(registry.triggered(&custom("ctrl one")));
+        assert!(!registry.triggered(&custom("one")));
+        assert!(registry.take(&custom("ctrl one")));
+        assert!(!registry.take(&custom("ctrl one")));
+
+        press(&mut registry, &[(Modifiers::NONE, Key::Escape)], declare);
+        assert!(registry.take(&ShortcutAction::Back));
+        press(&mut registry, &[], declare);
+        assert!(!registry.triggered(&ShortcutAction::Back));
+    }
+}
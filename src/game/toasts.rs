@@ -0,0 +1,173 @@
+use crate::sprintln;
+use egui::{Align2, Context, Id, Sense};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Warn,
+    Error,
+}
+impl Display for ToastLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToastLevel::Info => write!(f, "Info"),
+            ToastLevel::Warn => write!(f, "Warning"),
+            ToastLevel::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub level: ToastLevel,
+    pub message: String,
+    /// Seconds the toast stays visible
+    pub timeout: f64,
+    /// Time of the frame the toast got shown first, see [`egui::InputState::time`]
+    shown_since: Option<f64>,
+}
+/// Builder
+impl Toast {
+    pub fn new(level: ToastLevel, message: impl Into<String>) -> Self {
+        let timeout = match level {
+            ToastLevel::Info => 3.0,
+            ToastLevel::Warn => 5.0,
+            ToastLevel::Error => 8.0,
+        };
+        Self {
+            level,
+            message: message.into(),
+            timeout,
+            shown_since: None,
+        }
+    }
+    pub fn timeout(self, timeout: f64) -> Self {
+        Toast { timeout, ..self }
+    }
+}
+
+/// App-wide queue of notifications, shown in the bottom right corner until they time
+/// out or get clicked.
+///
+/// It's a cheap handle, so screens and card loaders keep a clone and push into it
+/// from wherever they are, e.g. from inside a [`wasm_bindgen_futures::spawn_local`] task.
+/// Every toast is logged through [`sprintln!`](crate::sprintln) as well.
+#[derive(Clone, Default)]
+pub struct Toasts {
+    queue: Rc<RefCell<VecDeque<Toast>>>,
+}
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&self, toast: Toast) {
+        sprintln!("{}: {}", toast.level, toast.message);
+        self.queue.borrow_mut().push_back(toast);
+    }
+    pub fn info(&self, message: impl Into<String>) {
+        self.push(Toast::new(ToastLevel::Info, message));
+    }
+    pub fn warn(&self, message: impl Into<String>) {
+        self.push(Toast::new(ToastLevel::Warn, message));
+    }
+    pub fn error(&self, message: impl Into<String>) {
+        self.push(Toast::new(ToastLevel::Error, message));
+    }
+    pub fn len(&self) -> usize {
+        self.queue.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue.borrow().is_empty()
+    }
+    pub fn clear(&self) {
+        self.queue.borrow_mut().clear();
+    }
+}
+/// Internal
+impl Toasts {
+    /// Draws all toasts, newest at the bottom, and drops the expired or clicked ones.
+    pub(crate) fn show(&self, ctx: &Context) {
+        let mut queue = self.queue.borrow_mut();
+        if queue.is_empty() {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        queue.retain(|toast| {
+            toast
+                .shown_since
+                .is_none_or(|since| now - since < toast.timeout)
+        });
+        let mut dismissed = None;
+        egui::Area::new(Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .order(egui::Order::Tooltip)
+            .show(ctx, |ui| {
+                ui.set_max_width(300.0);
+                for (idx, toast) in queue.iter_mut().enumerate() {
+                    let since = *toast.shown_since.get_or_insert(now);
+                    ctx.request_repaint_after_secs((since + toast.timeout - now) as f32);
+                    let color = match toast.level {
+                        ToastLevel::Info => ui.visuals().text_color(),
+                        ToastLevel::Warn => ui.visuals().warn_fg_color,
+                        ToastLevel::Error => ui.visuals().error_fg_color,
+                    };
+                    let response = egui::Frame::popup(ui.style())
+                        .stroke((1.0, color))
+                        .show(ui, |ui| {
+                            ui.colored_label(color, toast.level.to_string());
+                            ui.label(&toast.message);
+                        })
+                        .response
+                        .interact(Sense::click())
+                        .on_hover_text("Click to dismiss");
+                    if response.clicked() {
+                        dismissed = Some(idx);
+                    }
+                }
+            });
+        if let Some(idx) = dismissed {
+            queue.remove(idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::RawInput;
+
+    fn frame(ctx: &Context, toasts: &Toasts, time: f64) {
+        let input = RawInput {
+            time: Some(time),
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| toasts.show(ctx));
+    }
+
+    #[test]
+    fn toasts_expire_after_being_shown_for_their_timeout() {
+        let ctx = Context::default();
+        let toasts = Toasts::new();
+        toasts.info("info");
+        toasts.error("error");
+        toasts.push(Toast::new(ToastLevel::Warn, "short").timeout(1.0));
+        // Time only counts once a toast is on screen
+        frame(&ctx, &toasts, 100.0);
+        assert_eq!(toasts.len(), 3);
+        frame(&ctx, &toasts, 100.5);
+        assert_eq!(toasts.len(), 3);
+        frame(&ctx, &toasts, 101.0);
+        assert_eq!(toasts.len(), 2);
+        toasts.warn("late");
+        frame(&ctx, &toasts, 103.0);
+        assert_eq!(toasts.len(), 2);
+        frame(&ctx, &toasts, 107.9);
+        assert_eq!(toasts.len(), 2);
+        frame(&ctx, &toasts, 108.0);
+        assert_eq!(toasts.len(), 0);
+    }
+}
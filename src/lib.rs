@@ -4,8 +4,11 @@ mod utils;
 
 #[allow(unused_imports)]
 use eframe::AppCreator;
+#[cfg(not(target_arch = "wasm32"))]
+use eframe::NativeOptions;
 #[cfg(target_arch = "wasm32")]
 use eframe::{WebOptions, WebRunner};
+use game::card::assets::AssetSource;
 #[allow(unused_imports)]
 use wasm_bindgen::prelude::*;
 #[allow(unused_imports)]
@@ -21,13 +24,16 @@ extern "C" {
     pub fn openDirectoryPicker() -> Promise;
 }
 
+/// Starts the web build on `canvas`, card images get resolved through `assets`.
 #[cfg(target_arch = "wasm32")]
 pub fn start_game(
     canvas: web_sys::HtmlCanvasElement,
+    assets: impl AssetSource + 'static,
     init: AppCreator<'static>,
 ) -> Result<(), JsValue> {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
+    let init = with_assets(assets, init);
     let web_options = WebOptions::default();
     spawn_local(async move {
         WebRunner::new()
@@ -38,6 +44,34 @@ pub fn start_game(
     Ok(())
 }
 
+/// Opens the native window, card images get resolved through `assets`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_native(assets: impl AssetSource + 'static, init: AppCreator<'static>) -> eframe::Result {
+    let init = with_assets(assets, init);
+    let native_options = NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("MCG Visual")
+            .with_inner_size([1280.0, 800.0]),
+        ..Default::default()
+    };
+    eframe::run_native("mcg_visual", native_options, init)
+}
+
+/// Selects `assets` as [`source`](game::card::assets::source) and installs it into egui's
+/// context before `init` runs, together with the
+/// [`default_deck`](game::card::embedded::default_deck).
+fn with_assets(
+    assets: impl AssetSource + 'static,
+    init: AppCreator<'static>,
+) -> AppCreator<'static> {
+    game::card::assets::set_source(assets);
+    Box::new(move |cc| {
+        game::card::assets::source().install(&cc.egui_ctx);
+        game::card::embedded::default_deck().install(&cc.egui_ctx);
+        init(cc)
+    })
+}
+
 /* TODO implement right-click with popup when this
     https://github.com/emilk/egui/blob/master/crates/egui/src/containers/popup.rs
     gets into a proper egui release
@@ -0,0 +1,18 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> eframe::Result {
+    use mcg_visual::game::card::assets::FileSource;
+    use mcg_visual::game::App;
+    // Relative card folders, e.g. the one of `example::ConventionalCard`, are looked up here
+    let root = std::env::var_os("MCG_MEDIA_DIR").unwrap_or_else(|| "media".into());
+    mcg_visual::run_native(
+        FileSource::new(root),
+        Box::new(|cc| {
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Ok(Box::new(App::new(None).with_storage(cc.storage)))
+        }),
+    )
+}
+
+// The web build is started through `mcg_visual::start_game` instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -9,5 +9,5 @@ wasm_bindgen_test_configure!(run_in_browser);
 
 #[wasm_bindgen_test]
 fn pass() {
-    assert_eq!(1 + 1, "2".parse().unwrap());
+    assert_eq!(1 + 1, "2".parse::<i32>().unwrap());
 }